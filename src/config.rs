@@ -0,0 +1,233 @@
+use crossterm::event::KeyCode;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::tui::{key_name, KeyBindings};
+
+/// Defaults read from `$XDG_CONFIG_HOME/keyzen/config`, a JSON object such as
+/// `{"duration": 60, "lang": "rust", "keys": {"restart": "enter"}}`. Command
+/// line flags take precedence over anything set here.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub duration: Option<u32>,
+    pub lang: Option<String>,
+    pub mode: Option<String>,
+    pub theme: Option<String>,
+    pub words_per_line: Option<usize>,
+    pub visible_lines: Option<usize>,
+    pub allow_paste: bool,
+    pub sound: Option<bool>,
+    pub keys: KeyBindings,
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("keyzen"))
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config"))
+}
+
+/// Loads the config file. A missing file gives the built-in defaults.
+pub fn load_config() -> Result<Config, String> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+    };
+
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+    Config::from_json(&value, &path.display().to_string())
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+}
+
+impl Config {
+    fn from_json(value: &Value, path: &str) -> Result<Self, String> {
+        let Some(settings) = value.as_object() else {
+            return Err("expected a JSON object".to_string());
+        };
+
+        let mut config = Config::default();
+        for (key, value) in settings {
+            match key.as_str() {
+                "duration" => config.duration = Some(positive(key, value)? as u32),
+                "lang" => config.lang = Some(string(key, value)?),
+                "mode" => config.mode = Some(string(key, value)?),
+                "theme" => config.theme = Some(string(key, value)?),
+                "words_per_line" => config.words_per_line = Some(positive(key, value)? as usize),
+                "visible_lines" => config.visible_lines = Some(positive(key, value)? as usize),
+                "allow_paste" => config.allow_paste = boolean(key, value)?,
+                "sound" => config.sound = Some(boolean(key, value)?),
+                "keys" => config.keys = key_bindings(value)?,
+                _ => eprintln!("Ignoring unknown setting '{}' in {}", key, path),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn positive(key: &str, value: &Value) -> Result<u64, String> {
+    match value.as_u64() {
+        Some(n) if n > 0 && n <= u32::MAX as u64 => Ok(n),
+        _ => Err(format!("'{}' must be a positive whole number", key)),
+    }
+}
+
+fn string(key: &str, value: &Value) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("'{}' must be a string", key))
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("'{}' must be true or false", key))
+}
+
+fn key_bindings(value: &Value) -> Result<KeyBindings, String> {
+    let Some(bindings) = value.as_object() else {
+        return Err("'keys' must be an object".to_string());
+    };
+
+    let mut keys = KeyBindings::default();
+    for (action, name) in bindings {
+        let name = string(&format!("keys.{}", action), name)?;
+        let code = parse_key(&name)
+            .ok_or_else(|| format!("unknown key '{}' for keys.{}", name, action))?;
+        match action.as_str() {
+            "restart" => keys.restart = code,
+            "retry" => keys.retry = code,
+            "quit" => keys.quit = code,
+            // Ending a test early can't be a key that gets typed, and enter
+            // types line breaks in code.
+            "finish" if matches!(code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter) => {
+                return Err(format!(
+                    "keys.finish can't be '{}', a key used for typing",
+                    name
+                ));
+            }
+            "finish" => keys.finish = code,
+            _ => return Err(format!("unknown key binding 'keys.{}'", action)),
+        }
+    }
+
+    // The results screen checks these in turn, so a shared key would leave
+    // the later action unreachable.
+    let results = [
+        ("restart", keys.restart),
+        ("retry", keys.retry),
+        ("quit", keys.quit),
+    ];
+    for (i, (action, code)) in results.iter().enumerate() {
+        if let Some((other, _)) = results[i + 1..].iter().find(|(_, c)| c == code) {
+            return Err(format!(
+                "keys.{} and keys.{} can't both be '{}'",
+                action,
+                other,
+                key_name(*code)
+            ));
+        }
+    }
+    Ok(keys)
+}
+
+/// Parses a key name like `tab`, `esc`, `f5` or a single character.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let lower = name.to_lowercase();
+    let code = match lower.as_str() {
+        "tab" => KeyCode::Tab,
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        _ => {
+            if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                if (1..=12).contains(&n) {
+                    return Some(KeyCode::F(n));
+                }
+            }
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_key_knows_named_keys_in_any_case() {
+        assert_eq!(parse_key("Tab"), Some(KeyCode::Tab));
+        assert_eq!(parse_key("escape"), Some(KeyCode::Esc));
+        assert_eq!(parse_key("RETURN"), Some(KeyCode::Enter));
+        assert_eq!(parse_key("space"), Some(KeyCode::Char(' ')));
+        assert_eq!(parse_key("f5"), Some(KeyCode::F(5)));
+        assert_eq!(parse_key("f13"), None);
+    }
+
+    #[test]
+    fn parse_key_takes_a_single_character_as_is() {
+        assert_eq!(parse_key("R"), Some(KeyCode::Char('R')));
+        assert_eq!(parse_key("é"), Some(KeyCode::Char('é')));
+        assert_eq!(parse_key("f"), Some(KeyCode::Char('f')));
+        assert_eq!(parse_key("ctrl"), None);
+        assert_eq!(parse_key(""), None);
+    }
+
+    #[test]
+    fn key_bindings_override_the_defaults() {
+        let keys = key_bindings(&json!({"restart": "enter", "finish": "f1"})).unwrap();
+        assert_eq!(keys.restart, KeyCode::Enter);
+        assert_eq!(keys.finish, KeyCode::F(1));
+        assert_eq!(keys.retry, KeyBindings::default().retry);
+    }
+
+    #[test]
+    fn key_bindings_refuse_typing_keys_for_finish() {
+        for name in ["a", "space", "backspace", "enter"] {
+            assert!(
+                key_bindings(&json!({ "finish": name })).is_err(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn key_bindings_refuse_shared_results_keys() {
+        assert_eq!(
+            key_bindings(&json!({"restart": "esc"})).unwrap_err(),
+            "keys.restart and keys.quit can't both be 'esc'"
+        );
+        assert!(key_bindings(&json!({"quit": "tab"})).is_err());
+        assert!(key_bindings(&json!({"retry": "tab"})).is_err());
+        // Finish is only used during the test, so it can share with quit.
+        assert!(key_bindings(&json!({"finish": "tab", "restart": "f2"})).is_ok());
+    }
+
+    #[test]
+    fn key_bindings_reject_unknown_names() {
+        assert!(key_bindings(&json!({"pause": "p"})).is_err());
+        assert!(key_bindings(&json!({"quit": "ctrl"})).is_err());
+        assert!(key_bindings(&json!("tab")).is_err());
+    }
+}
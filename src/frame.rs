@@ -0,0 +1,233 @@
+use crossterm::{
+    cursor,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::fmt::Display;
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cell {
+    /// The grapheme shown in this cell, or empty for the second column of a
+    /// double-width grapheme in the cell before.
+    symbol: String,
+    fg: Color,
+    bg: Color,
+}
+
+const BLANK: Cell = Cell {
+    symbol: String::new(),
+    fg: Color::Reset,
+    bg: Color::Reset,
+};
+
+/// An off-screen grid of styled graphemes the size of the terminal.
+///
+/// Drawing calls mirror the crossterm commands they stand in for, but only
+/// update the grid. [`Frame::render`] then writes the cells that differ from
+/// the previously rendered frame in one batch, so a screen that barely
+/// changes between ticks costs almost nothing to redraw and never flickers.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    x: u16,
+    y: u16,
+    fg: Color,
+    bg: Color,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![BLANK; width as usize * height as usize],
+            x: 0,
+            y: 0,
+            fg: Color::Reset,
+            bg: Color::Reset,
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_fg(&mut self, color: Color) {
+        self.fg = color;
+    }
+
+    pub fn set_bg(&mut self, color: Color) {
+        self.bg = color;
+    }
+
+    pub fn reset_color(&mut self) {
+        self.fg = Color::Reset;
+        self.bg = Color::Reset;
+    }
+
+    /// Writes text at the cursor in the current colours, one cell per
+    /// grapheme, or two for wide ones such as CJK characters. Anything past
+    /// the right or bottom edge is dropped.
+    pub fn print(&mut self, text: impl Display) {
+        for grapheme in text.to_string().graphemes(true) {
+            let width = grapheme.width().clamp(1, 2) as u16;
+            if self.x.saturating_add(width) <= self.width && self.y < self.height {
+                let index = self.y as usize * self.width as usize + self.x as usize;
+                self.cells[index] = Cell {
+                    symbol: grapheme.to_string(),
+                    fg: self.fg,
+                    bg: self.bg,
+                };
+                if width == 2 {
+                    self.cells[index + 1] = Cell {
+                        symbol: String::new(),
+                        fg: self.fg,
+                        bg: self.bg,
+                    };
+                }
+            }
+            self.x = self.x.saturating_add(width);
+        }
+    }
+
+    /// Draws the frame, writing only cells that changed since `previous`.
+    /// Without a previous frame of the same size the screen is cleared and
+    /// drawn in full.
+    pub fn render(&self, out: &mut impl Write, previous: Option<&Frame>) -> io::Result<()> {
+        let previous = previous.filter(|p| (p.width, p.height) == (self.width, self.height));
+        if previous.is_none() {
+            out.queue(ResetColor)?;
+            out.queue(terminal::Clear(ClearType::All))?;
+        }
+
+        let mut position = None;
+        let mut fg = None;
+        let mut bg = None;
+        for (index, cell) in self.cells.iter().enumerate() {
+            let unchanged = match previous {
+                Some(previous) => previous.cells[index] == *cell,
+                None => *cell == BLANK,
+            };
+            if unchanged {
+                continue;
+            }
+            // Covered by the wide grapheme before it, which is redrawn too
+            // whenever it changes.
+            if cell.symbol.is_empty()
+                && index % (self.width as usize) > 0
+                && self.cells[index - 1].symbol.width() == 2
+            {
+                continue;
+            }
+
+            let x = (index % self.width as usize) as u16;
+            let y = (index / self.width as usize) as u16;
+            if position != Some((x, y)) {
+                out.queue(cursor::MoveTo(x, y))?;
+            }
+            if fg != Some(cell.fg) {
+                out.queue(SetForegroundColor(cell.fg))?;
+                fg = Some(cell.fg);
+            }
+            if bg != Some(cell.bg) {
+                out.queue(SetBackgroundColor(cell.bg))?;
+                bg = Some(cell.bg);
+            }
+            if cell.symbol.is_empty() {
+                out.queue(Print(' '))?;
+                position = Some((x + 1, y));
+            } else {
+                out.queue(Print(&cell.symbol))?;
+                position = Some((x + cell.symbol.width().clamp(1, 2) as u16, y));
+            }
+        }
+
+        if fg.is_some() || bg.is_some() {
+            out.queue(ResetColor)?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(frame: &Frame, previous: Option<&Frame>) -> String {
+        let mut out = Vec::new();
+        frame.render(&mut out, previous).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn frame_with(width: u16, height: u16, x: u16, y: u16, text: &str) -> Frame {
+        let mut frame = Frame::new(width, height);
+        frame.move_to(x, y);
+        frame.print(text);
+        frame
+    }
+
+    #[test]
+    fn first_render_clears_the_screen_and_draws_everything() {
+        let frame = frame_with(10, 2, 2, 1, "hi");
+        let out = render(&frame, None);
+        assert!(out.contains("\x1b[2J"));
+        assert!(out.contains("\x1b[2;3H"));
+        assert!(out.contains("hi"));
+    }
+
+    #[test]
+    fn unchanged_frame_writes_nothing() {
+        let frame = frame_with(10, 2, 2, 1, "hi");
+        assert_eq!(render(&frame, Some(&frame.clone())), "");
+    }
+
+    #[test]
+    fn only_changed_cells_are_written() {
+        let previous = frame_with(10, 2, 0, 0, "abc");
+        let frame = frame_with(10, 2, 0, 0, "abx");
+        assert_eq!(
+            render(&frame, Some(&previous)),
+            "\x1b[1;3H\x1b[39m\x1b[49mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn second_column_of_a_wide_grapheme_is_skipped() {
+        let previous = Frame::new(10, 1);
+        let frame = frame_with(10, 1, 0, 0, "日");
+        assert_eq!(
+            render(&frame, Some(&previous)),
+            "\x1b[1;1H\x1b[39m\x1b[49m日\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn wide_grapheme_replaced_by_narrow_ones_is_fully_redrawn() {
+        let previous = frame_with(10, 1, 0, 0, "日");
+        let frame = frame_with(10, 1, 0, 0, "ab");
+        assert_eq!(
+            render(&frame, Some(&previous)),
+            "\x1b[1;1H\x1b[39m\x1b[49mab\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn size_change_clears_the_screen() {
+        let previous = frame_with(10, 2, 0, 0, "hi");
+        let frame = frame_with(12, 2, 0, 0, "hi");
+        let out = render(&frame, Some(&previous));
+        assert!(out.contains("\x1b[2J"));
+        assert!(out.contains("hi"));
+    }
+}
@@ -1,6 +1,1608 @@
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::lang::{Quote, QuoteLength};
 
 pub fn get_random_words(words: &[String], count: usize) -> Vec<String> {
     let mut rng = rand::thread_rng();
     words.choose_multiple(&mut rng, count).cloned().collect()
-}
\ No newline at end of file
+}
+
+/// Draws `count` words, with replacement, where each word's chance of being
+/// picked is proportional to `weight(word)`.
+pub fn get_weighted_words(
+    words: &[String],
+    count: usize,
+    weight: impl Fn(&str) -> f64,
+) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    match WeightedIndex::new(words.iter().map(|w| weight(w))) {
+        Ok(index) => (0..count)
+            .map(|_| words[index.sample(&mut rng)].clone())
+            .collect(),
+        Err(_) => get_random_words(words, count),
+    }
+}
+
+/// The `per_size` most common bigrams and trigrams inside the given words,
+/// most common first.
+pub fn common_ngrams(words: &[String], per_size: usize) -> Vec<String> {
+    let mut ngrams = Vec::new();
+    for size in [2, 3] {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in words {
+            let chars: Vec<char> = word.chars().collect();
+            for window in chars.windows(size) {
+                *counts.entry(window.iter().collect()).or_default() += 1;
+            }
+        }
+        let mut counted: Vec<(String, usize)> = counts.into_iter().collect();
+        counted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ngrams.extend(counted.into_iter().take(per_size).map(|(ngram, _)| ngram));
+    }
+    ngrams
+}
+
+pub fn get_random_snippet(snippets: &[String]) -> Option<String> {
+    let mut rng = rand::thread_rng();
+    snippets.choose(&mut rng).cloned()
+}
+
+pub fn get_random_quote(quotes: &[Quote], length: Option<QuoteLength>) -> Option<Quote> {
+    let mut rng = rand::thread_rng();
+    let matching: Vec<&Quote> = quotes
+        .iter()
+        .filter(|q| length.is_none() || length == Some(q.length))
+        .collect();
+    matching.choose(&mut rng).map(|q| (*q).clone())
+}
+
+pub fn generate_unlimited_words(original_words: &[String], estimated_needed: usize) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let mut result = Vec::new();
+
+    if original_words.is_empty() {
+        return result;
+    }
+
+    while result.len() < estimated_needed {
+        let batch_size = original_words.len().min(estimated_needed - result.len());
+        let mut batch: Vec<String> = original_words
+            .choose_multiple(&mut rng, batch_size)
+            .cloned()
+            .collect();
+        result.append(&mut batch);
+    }
+
+    result
+}
+
+/// Optional transformations applied to randomly drawn words so they read
+/// more like real prose.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WordModifiers {
+    pub punctuation: bool,
+    pub numbers: bool,
+}
+
+impl WordModifiers {
+    pub fn is_empty(&self) -> bool {
+        !self.punctuation && !self.numbers
+    }
+
+    /// Short tags for the modifiers in use, e.g. `+punct +num`, or empty.
+    pub fn label(&self) -> String {
+        let tags = [(self.punctuation, "+punct"), (self.numbers, "+num")];
+        tags.iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, tag)| *tag)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Applies the modifiers to `words`. `sentence_start` says whether the
+    /// first word begins a new sentence, so consecutive batches join up.
+    pub fn apply(&self, words: Vec<String>, mut sentence_start: bool) -> Vec<String> {
+        if self.is_empty() {
+            return words;
+        }
+
+        let mut rng = rand::thread_rng();
+        words
+            .into_iter()
+            .map(|word| {
+                let mut word = if self.numbers && rng.gen_bool(0.1) {
+                    let digits = rng.gen_range(1..=4);
+                    rng.gen_range(0..10u32.pow(digits)).to_string()
+                } else {
+                    word
+                };
+
+                if !self.punctuation {
+                    return word;
+                }
+
+                if sentence_start {
+                    word = capitalize(&word);
+                }
+
+                match rng.gen_range(0..100) {
+                    0..=5 => word = format!("\"{}\"", word),
+                    6..=8 => word = format!("({})", word),
+                    _ => {}
+                }
+
+                let ending = match rng.gen_range(0..100) {
+                    0..=9 => ".",
+                    10..=12 => "?",
+                    13..=14 => "!",
+                    15..=24 => ",",
+                    25..=26 => ";",
+                    27..=28 => ":",
+                    _ => "",
+                };
+                sentence_start = matches!(ending, "." | "?" | "!");
+                word.push_str(ending);
+                word
+            })
+            .collect()
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn ends_sentence(word: &str) -> bool {
+    word.ends_with(['.', '?', '!'])
+}
+
+/// Splits text into user-perceived characters.
+fn graphemes(text: &str) -> Vec<String> {
+    text.graphemes(true).map(str::to_string).collect()
+}
+
+/// The character that should have been typed after `so_far` within the
+/// grapheme `expected`. Spelling the grapheme out with combining marks is
+/// as good as typing it precomposed.
+fn expected_char(expected: &str, so_far: &str, c: char) -> Option<char> {
+    let attempt: String = so_far.chars().chain([c]).nfd().collect();
+    if expected.nfd().collect::<String>().starts_with(&attempt) {
+        Some(c)
+    } else {
+        expected.chars().nth(so_far.chars().count())
+    }
+}
+
+fn is_whitespace(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+/// Splits user supplied text into words, keeping their original order.
+pub fn text_to_words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+/// Tidies a code snippet for typing: tabs become four spaces, trailing
+/// whitespace and blank lines at either end are dropped, and indentation
+/// shared by every line is removed.
+pub fn normalize_code(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| line.replace('\t', "    ").trim_end().to_string())
+        .collect();
+
+    let first = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let last = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(first, |i| i + 1);
+    let lines = &lines[first..last];
+
+    // Counted in characters, as pasted code can be indented with
+    // non-breaking or full-width spaces.
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|l| l.char_indices().nth(indent).map_or("", |(i, _)| &l[i..]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What ends a test: running out of time, typing a fixed number of random
+/// words, or reaching the end of a user supplied text, quote or code
+/// snippet. The value is the length of the text or quote in words, or of
+/// the code in lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestMode {
+    Time(u32),
+    Words(u32),
+    Text(u32),
+    Quote(u32),
+    Code(u32),
+}
+
+impl TestMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestMode::Time(_) => "time",
+            TestMode::Words(_) => "words",
+            TestMode::Text(_) => "text",
+            TestMode::Quote(_) => "quote",
+            TestMode::Code(_) => "code",
+        }
+    }
+
+    pub fn limit(&self) -> u32 {
+        match self {
+            TestMode::Time(secs) => *secs,
+            TestMode::Words(count)
+            | TestMode::Text(count)
+            | TestMode::Quote(count)
+            | TestMode::Code(count) => *count,
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        match self {
+            TestMode::Time(secs) => Some(Duration::from_secs(*secs as u64)),
+            TestMode::Words(_) | TestMode::Text(_) | TestMode::Quote(_) | TestMode::Code(_) => None,
+        }
+    }
+}
+
+/// What a drill session practises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drill {
+    /// Symbol sequences or common n-grams from `--ngrams`.
+    Ngrams,
+    /// The words missed in the previous test.
+    Retry,
+}
+
+impl Drill {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Drill::Ngrams => "ngrams",
+            Drill::Retry => "retry",
+        }
+    }
+}
+
+/// How mistakes are handled while typing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Every key press takes up the next character, so a space typed early
+    /// shifts everything after it.
+    #[default]
+    Stream,
+    /// Space moves on to the next word and the rest of the current word
+    /// counts as missed; letters typed past the end of a word are extra
+    /// errors, and backspace only goes back into a word with mistakes.
+    Word,
+}
+
+/// Mistakes that must be fixed before the caret moves on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    /// A wrong key is refused and the caret waits for the right one.
+    Letter,
+    /// The caret can't move past a word until its mistakes are fixed.
+    Word,
+}
+
+/// Options for how a session treats what is typed, the same for every test
+/// in a run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rules {
+    pub error_mode: ErrorMode,
+    pub strict: Option<Strictness>,
+    /// End the test as failed on the first mistake.
+    pub sudden_death: bool,
+    /// Fail the test as soon as accuracy drops below this percentage, once
+    /// at least [`MIN_ACCURACY_CHARS`] characters have been typed.
+    pub min_accuracy: Option<u32>,
+    /// Fail the test if speed is below this once [`MIN_WPM_GRACE`] is up.
+    pub min_wpm: Option<u32>,
+}
+
+/// Time allowed to get up to speed before `min_wpm` is enforced.
+pub const MIN_WPM_GRACE: Duration = Duration::from_secs(5);
+
+/// Characters typed before `min_accuracy` is enforced, so one slip on the
+/// first key doesn't end the test.
+pub const MIN_ACCURACY_CHARS: usize = 10;
+
+/// Why a test ended early as failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    Mistake,
+    Accuracy { accuracy: f64, min: u32 },
+    Speed { wpm: f64, min: u32 },
+}
+
+impl Failure {
+    pub fn reason(&self) -> String {
+        match self {
+            Failure::Mistake => "mistake in sudden death".to_string(),
+            Failure::Accuracy { accuracy, min } => {
+                format!("accuracy {:.0}% fell below {}%", accuracy, min)
+            }
+            Failure::Speed { wpm, min } => format!("{:.0} wpm fell below {} wpm", wpm, min),
+        }
+    }
+}
+
+/// Most extra characters kept past the end of one word in word error mode;
+/// further ones are ignored.
+const MAX_OVERFLOW: usize = 10;
+
+/// Gaps between key presses longer than this are pauses rather than time
+/// spent reaching the key, so they are left out of per-key and per-word
+/// timings.
+pub const MAX_KEY_GAP: Duration = Duration::from_secs(2);
+
+/// A single key press, timed from the start of the test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keystroke {
+    pub at: Duration,
+    pub action: KeyAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// A character, along with the position in the target of the grapheme
+    /// it was typed into and the character it was typed against: the one at
+    /// the same place within the expected grapheme, or `None` past its end.
+    /// Extra characters past the end of a word in word error mode carry the
+    /// position of the word's last grapheme.
+    Char {
+        typed: char,
+        index: usize,
+        expected: Option<char>,
+    },
+    Backspace,
+}
+
+impl Keystroke {
+    pub fn is_correct(&self) -> bool {
+        matches!(self.action, KeyAction::Char { typed, expected, .. } if Some(typed) == expected)
+    }
+}
+
+/// Typing speed during one second of a test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedSample {
+    /// Average speed from the start of the test to the end of this second.
+    pub wpm: f64,
+    /// Speed counting every keystroke made within this second alone.
+    pub raw_wpm: f64,
+    pub errors: u32,
+}
+
+/// Time and mistakes spent on every occurrence of one word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordStat {
+    pub word: String,
+    pub chars: u32,
+    pub errors: u32,
+    pub time: Duration,
+}
+
+impl WordStat {
+    /// Typing speed within the word itself, ignoring the gaps between words.
+    pub fn wpm(&self) -> f64 {
+        let minutes = self.time.as_secs_f64() / 60.0;
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        (self.chars - self.errors.min(self.chars)) as f64 / 5.0 / minutes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharStatus {
+    Correct,
+    Incorrect,
+    Pending,
+}
+
+/// Scoring state for a single typing test, independent of any front end.
+///
+/// The target is a flat stream of grapheme clusters, either words joined by
+/// single spaces or a verbatim text such as a code snippet, so an accented
+/// letter or an emoji counts as one character however many code points it
+/// is made of. Both the target and the typed text are kept in Unicode
+/// normalization form C so precomposed and combining input compare equal.
+/// Keystrokes are fed in with the instant they happened so the session
+/// never has to read the clock itself.
+///
+/// Timed sessions expect the caller to keep topping up the target with
+/// `extend_from_pool`; every other mode ends once the whole target is typed.
+pub struct TypingSession {
+    words: Vec<String>,
+    word_pool: Vec<String>,
+    word_modifiers: WordModifiers,
+    source: Option<String>,
+    drill: Option<Drill>,
+    target: Vec<String>,
+    /// One entry per target position typed into. A character that combines
+    /// with the last one typed, such as an accent, joins its grapheme.
+    typed: Vec<String>,
+    /// Parallel to `typed`: true for indentation filled in by the session
+    /// rather than typed by the user.
+    auto_typed: Vec<bool>,
+    auto_indent: bool,
+    /// Extra graphemes typed past the end of a word in word error mode,
+    /// keyed by the position of the space after the word.
+    overflow: BTreeMap<usize, Vec<String>>,
+    rules: Rules,
+    mode: TestMode,
+    start_time: Option<Instant>,
+    end_time: Option<Instant>,
+    correct_chars: usize,
+    total_typed_chars: usize,
+    /// Mistakes fixed along the way: wrong keys refused in strict letter
+    /// mode and wrong characters deleted with backspace.
+    corrections: usize,
+    failure: Option<Failure>,
+    keystrokes: Vec<Keystroke>,
+}
+
+impl TypingSession {
+    pub fn new(words: Vec<String>, mode: TestMode) -> Self {
+        let mut session = Self {
+            words: Vec::new(),
+            word_pool: Vec::new(),
+            word_modifiers: WordModifiers::default(),
+            source: None,
+            drill: None,
+            target: Vec::new(),
+            typed: Vec::new(),
+            auto_typed: Vec::new(),
+            auto_indent: false,
+            overflow: BTreeMap::new(),
+            rules: Rules::default(),
+            mode,
+            start_time: None,
+            end_time: None,
+            correct_chars: 0,
+            total_typed_chars: 0,
+            corrections: 0,
+            failure: None,
+            keystrokes: Vec::new(),
+        };
+        session.push_words(words);
+        session
+    }
+
+    /// Appends more words to the end of the target text.
+    pub fn push_words(&mut self, words: Vec<String>) {
+        for word in words {
+            if !self.target.is_empty() {
+                self.target.push(" ".to_string());
+            }
+            let word: String = word.nfc().collect();
+            self.target.extend(graphemes(&word));
+            self.words.push(word);
+        }
+    }
+
+    /// Creates a timed session that draws its words at random from `pool`,
+    /// transformed by `modifiers`.
+    pub fn timed(pool: Vec<String>, secs: u32, modifiers: WordModifiers) -> Self {
+        let mut session = Self::new(Vec::new(), TestMode::Time(secs));
+        session.word_pool = pool;
+        session.word_modifiers = modifiers;
+        session
+    }
+
+    /// Creates a session whose target is `text` exactly as given, line
+    /// breaks and indentation included.
+    pub fn from_text(text: &str, mode: TestMode) -> Self {
+        let mut session = Self::new(Vec::new(), mode);
+        let text: String = text.nfc().collect();
+        session.target = graphemes(&text);
+        session.words = text_to_words(&text);
+        session
+    }
+
+    /// Records the modifiers a fixed list of words was drawn with. Timed
+    /// sessions get theirs from [`TypingSession::timed`].
+    pub fn set_word_modifiers(&mut self, modifiers: WordModifiers) {
+        self.word_modifiers = modifiers;
+    }
+
+    pub fn word_modifiers(&self) -> WordModifiers {
+        self.word_modifiers
+    }
+
+    /// When enabled, typing a line break also fills in the indentation at
+    /// the start of the next line.
+    pub fn set_auto_indent(&mut self, auto_indent: bool) {
+        self.auto_indent = auto_indent;
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Records where the target text comes from, e.g. a quote's author.
+    pub fn set_source(&mut self, source: String) {
+        self.source = Some(source);
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Marks the session as a drill of a few words or sequences, whose
+    /// results are reported per word.
+    pub fn set_drill(&mut self, drill: Drill) {
+        self.drill = Some(drill);
+    }
+
+    pub fn drill(&self) -> Option<Drill> {
+        self.drill
+    }
+
+    pub fn is_drill(&self) -> bool {
+        self.drill.is_some()
+    }
+
+    pub fn has_word_pool(&self) -> bool {
+        !self.word_pool.is_empty()
+    }
+
+    /// Appends `count` more random words from the word pool, if any.
+    pub fn extend_from_pool(&mut self, count: usize) {
+        let sentence_start = self.words.last().map(|w| ends_sentence(w)).unwrap_or(true);
+        let more = generate_unlimited_words(&self.word_pool, count);
+        let more = self.word_modifiers.apply(more, sentence_start);
+        self.push_words(more);
+    }
+
+    pub fn mode(&self) -> TestMode {
+        self.mode
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Number of words the caret has moved past.
+    pub fn words_typed(&self) -> usize {
+        let caret = self.caret().min(self.target.len());
+        (0..caret)
+            .filter(|&i| {
+                !is_whitespace(&self.target[i])
+                    && !matches!(self.target.get(i + 1), Some(g) if !is_whitespace(g))
+            })
+            .count()
+    }
+
+    pub fn target(&self) -> &[String] {
+        &self.target
+    }
+
+    pub fn typed(&self) -> &[String] {
+        &self.typed
+    }
+
+    pub fn keystrokes(&self) -> &[Keystroke] {
+        &self.keystrokes
+    }
+
+    /// Index into the target of the next grapheme to be typed.
+    pub fn caret(&self) -> usize {
+        self.typed.len()
+    }
+
+    pub fn char_status(&self, index: usize) -> CharStatus {
+        match self.typed.get(index) {
+            Some(_) if self.is_correct_at(index) => CharStatus::Correct,
+            Some(_) => CharStatus::Incorrect,
+            None => CharStatus::Pending,
+        }
+    }
+
+    /// Extra graphemes typed past the end of the word just before `index`.
+    pub fn overflow(&self, index: usize) -> &[String] {
+        self.overflow.get(&index).map_or(&[], Vec::as_slice)
+    }
+
+    fn is_correct_at(&self, index: usize) -> bool {
+        matches!(
+            (self.typed.get(index), self.target.get(index)),
+            (Some(typed), Some(expected)) if typed == expected
+        )
+    }
+
+    pub fn type_char(&mut self, c: char, now: Instant) {
+        if self.end_time.is_some() {
+            return;
+        }
+        if self.start_time.is_none() {
+            self.start_time = Some(now);
+        }
+
+        // A combining mark, a zero-width joiner and the like extend the
+        // grapheme typed last rather than starting a new one.
+        let joins = self.auto_typed.last() == Some(&false)
+            && self.typed.last().is_some_and(|last| {
+                let mut joined = last.clone();
+                joined.push(c);
+                joined.graphemes(true).count() == 1
+            });
+
+        let index = if joins {
+            self.typed.len() - 1
+        } else {
+            self.typed.len()
+        };
+        let so_far = if joins {
+            self.typed[index].as_str()
+        } else {
+            ""
+        };
+        let expected = self
+            .target
+            .get(index)
+            .and_then(|grapheme| expected_char(grapheme, so_far, c));
+
+        match self.rules.strict {
+            Some(Strictness::Letter) if index < self.target.len() => {
+                // A grapheme left half typed, such as a letter still missing
+                // its accent, has to be finished first.
+                let (index, expected) = if !joins && index > 0 && !self.is_correct_at(index - 1) {
+                    let previous = index - 1;
+                    let expected = expected_char(&self.target[previous], &self.typed[previous], c);
+                    (previous, expected)
+                } else {
+                    (index, expected)
+                };
+                if expected != Some(c) {
+                    self.log_keystroke(
+                        now,
+                        KeyAction::Char {
+                            typed: c,
+                            index,
+                            expected,
+                        },
+                    );
+                    self.total_typed_chars += 1;
+                    self.corrections += 1;
+                    return;
+                }
+            }
+            Some(Strictness::Word) if !joins && self.blocked_by_word_errors() => return,
+            _ => {}
+        }
+
+        if self.rules.error_mode == ErrorMode::Word && !joins {
+            let caret = self.typed.len();
+            let at_gap = self.target.get(caret).is_some_and(|g| is_whitespace(g));
+            let word_started = caret > 0 && !is_whitespace(&self.target[caret - 1]);
+            if c == ' ' && !at_gap && caret < self.target.len() {
+                // A space before any of the word is typed is ignored.
+                if word_started && self.rules.strict.is_none() {
+                    self.skip_word(now);
+                }
+                return;
+            }
+            if !c.is_whitespace() && at_gap && word_started {
+                self.type_overflow(c, now);
+                return;
+            }
+        }
+
+        self.log_keystroke(
+            now,
+            KeyAction::Char {
+                typed: c,
+                index,
+                expected,
+            },
+        );
+
+        if joins {
+            if self.is_correct_at(index) {
+                self.correct_chars -= 1;
+            }
+            let mut joined = self.typed[index].clone();
+            joined.push(c);
+            self.typed[index] = joined.nfc().collect();
+        } else {
+            self.typed.push(c.to_string());
+            self.auto_typed.push(false);
+            self.total_typed_chars += 1;
+        }
+        if self.is_correct_at(index) {
+            self.correct_chars += 1;
+        }
+
+        if c == '\n' && self.auto_indent && self.target.get(index).is_some_and(|g| g == "\n") {
+            while let Some(next) = self.target.get(self.typed.len()) {
+                if next != " " {
+                    break;
+                }
+                self.typed.push(next.clone());
+                self.auto_typed.push(true);
+            }
+        }
+
+        self.finish_if_complete(now);
+    }
+
+    /// Leaves the rest of the current word missed and moves past the space
+    /// after it.
+    fn skip_word(&mut self, now: Instant) {
+        let caret = self.typed.len();
+        let expected = self.target[caret].chars().next();
+        self.log_keystroke(
+            now,
+            KeyAction::Char {
+                typed: ' ',
+                index: caret,
+                expected,
+            },
+        );
+        while self
+            .target
+            .get(self.typed.len())
+            .is_some_and(|g| !is_whitespace(g))
+        {
+            self.typed.push(String::new());
+            self.auto_typed.push(false);
+            self.total_typed_chars += 1;
+        }
+        if self.target.get(self.typed.len()).is_some_and(|g| g == " ") {
+            self.typed.push(" ".to_string());
+            self.auto_typed.push(false);
+            self.total_typed_chars += 1;
+            self.correct_chars += 1;
+        }
+        self.finish_if_complete(now);
+    }
+
+    /// Adds a grapheme typed past the end of the word before the caret.
+    fn type_overflow(&mut self, c: char, now: Instant) {
+        let caret = self.typed.len();
+        let extra = self.overflow.entry(caret).or_default();
+        if extra.len() >= MAX_OVERFLOW {
+            return;
+        }
+        extra.push(c.to_string());
+        self.log_keystroke(
+            now,
+            KeyAction::Char {
+                typed: c,
+                index: caret - 1,
+                expected: None,
+            },
+        );
+        self.total_typed_chars += 1;
+    }
+
+    fn finish_if_complete(&mut self, now: Instant) {
+        if self.mode.duration().is_some() || self.typed.len() < self.target.len() {
+            return;
+        }
+        // Under strict word the end of the target is a gap like any other,
+        // so the last word has to be fixed before the test can end.
+        if self.rules.strict == Some(Strictness::Word) && self.word_has_errors(self.target.len()) {
+            return;
+        }
+        self.end_time = Some(now);
+    }
+
+    /// Whether the word ending just before `gap` was typed with any
+    /// mistakes, extra characters included.
+    fn word_has_errors(&self, gap: usize) -> bool {
+        if !self.overflow(gap).is_empty() {
+            return true;
+        }
+        (0..gap)
+            .rev()
+            .take_while(|&i| !is_whitespace(&self.target[i]))
+            .any(|i| !self.is_correct_at(i))
+    }
+
+    /// In strict word mode, whether the next character would move past a
+    /// word, or the space after it, that still has mistakes in it. Nothing
+    /// can be typed past the end of the target, only backspace.
+    fn blocked_by_word_errors(&self) -> bool {
+        let caret = self.typed.len();
+        if caret >= self.target.len() {
+            return true;
+        }
+        let at_gap = self.target.get(caret).is_some_and(|g| is_whitespace(g));
+        let after_gap = caret > 0 && is_whitespace(&self.target[caret - 1]);
+        (at_gap && self.word_has_errors(caret))
+            || (after_gap && (!self.is_correct_at(caret - 1) || self.word_has_errors(caret - 1)))
+    }
+
+    pub fn backspace(&mut self, now: Instant) {
+        if self.end_time.is_some() || self.start_time.is_none() {
+            return;
+        }
+        if self.rules.error_mode == ErrorMode::Word {
+            let caret = self.typed.len();
+            if let Some(extra) = self.overflow.get_mut(&caret) {
+                extra.pop();
+                if extra.is_empty() {
+                    self.overflow.remove(&caret);
+                }
+                self.log_keystroke(now, KeyAction::Backspace);
+                self.total_typed_chars = self.total_typed_chars.saturating_sub(1);
+                self.corrections += 1;
+                return;
+            }
+            // Going back over a space is only for fixing the word before it.
+            let after_gap = caret > 0 && is_whitespace(&self.target[caret - 1]);
+            if after_gap && self.is_correct_at(caret - 1) && !self.word_has_errors(caret - 1) {
+                return;
+            }
+        }
+        self.log_keystroke(now, KeyAction::Backspace);
+        while self.auto_typed.last() == Some(&true) {
+            self.typed.pop();
+            self.auto_typed.pop();
+        }
+        if !self.typed.is_empty() {
+            if self.is_correct_at(self.typed.len() - 1) {
+                self.correct_chars = self.correct_chars.saturating_sub(1);
+            } else {
+                self.corrections += 1;
+            }
+            self.typed.pop();
+            self.auto_typed.pop();
+            self.total_typed_chars = self.total_typed_chars.saturating_sub(1);
+        }
+        // The letters skipped by an early space go along with it.
+        while self.typed.last().is_some_and(String::is_empty) {
+            self.typed.pop();
+            self.auto_typed.pop();
+            self.total_typed_chars = self.total_typed_chars.saturating_sub(1);
+            self.corrections += 1;
+        }
+    }
+
+    fn log_keystroke(&mut self, now: Instant, action: KeyAction) {
+        let at = self
+            .start_time
+            .map_or(Duration::ZERO, |start| now.saturating_duration_since(start));
+        let keystroke = Keystroke { at, action };
+        self.keystrokes.push(keystroke);
+        if self.rules.sudden_death
+            && matches!(action, KeyAction::Char { .. })
+            && !keystroke.is_correct()
+        {
+            self.fail(Failure::Mistake, now);
+        }
+    }
+
+    /// Fails the test if accuracy or speed has dropped below the minimums
+    /// set in its rules. Accuracy is only checked after
+    /// [`MIN_ACCURACY_CHARS`] characters and speed after [`MIN_WPM_GRACE`].
+    pub fn check_limits(&mut self, now: Instant) {
+        if self.end_time.is_some() {
+            return;
+        }
+        if let (Some(min), Some(accuracy)) = (self.rules.min_accuracy, self.accuracy()) {
+            if self.total_typed_chars >= MIN_ACCURACY_CHARS && accuracy < min as f64 {
+                self.fail(Failure::Accuracy { accuracy, min }, now);
+                return;
+            }
+        }
+        if let Some(min) = self.rules.min_wpm {
+            let wpm = self.wpm(now);
+            if self.elapsed(now) >= MIN_WPM_GRACE && wpm < min as f64 {
+                self.fail(Failure::Speed { wpm, min }, now);
+            }
+        }
+    }
+
+    fn fail(&mut self, failure: Failure, now: Instant) {
+        self.failure = Some(failure);
+        self.finish(now);
+    }
+
+    /// Why the test failed, if it did.
+    pub fn failure(&self) -> Option<Failure> {
+        self.failure
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.start_time.is_some()
+    }
+
+    /// Returns true once the test is over, either because `finish` was
+    /// called, the time limit has run out or the whole target was typed.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        if self.end_time.is_some() {
+            return true;
+        }
+        match (self.start_time, self.mode.duration()) {
+            (Some(start), Some(duration)) => now.saturating_duration_since(start) >= duration,
+            _ => false,
+        }
+    }
+
+    /// Whether the test ran its full course, the time running out or the
+    /// whole target being typed, rather than being ended early or failed.
+    pub fn completed(&self) -> bool {
+        let (Some(start), Some(end)) = (self.start_time, self.end_time) else {
+            return false;
+        };
+        if self.failure.is_some() {
+            return false;
+        }
+        match self.mode.duration() {
+            Some(duration) => end.saturating_duration_since(start) >= duration,
+            None => self.typed.len() >= self.target.len(),
+        }
+    }
+
+    /// Stops the session, freezing its elapsed time and scores.
+    pub fn finish(&mut self, now: Instant) {
+        if self.end_time.is_none() {
+            let end = match (self.start_time, self.mode.duration()) {
+                (Some(start), Some(duration)) => now.min(start + duration),
+                _ => now,
+            };
+            self.end_time = Some(end);
+        }
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        match self.start_time {
+            Some(start) => self
+                .end_time
+                .unwrap_or(now)
+                .saturating_duration_since(start),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Time remaining in a timed test, `None` in other modes.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.mode
+            .duration()
+            .map(|duration| duration.saturating_sub(self.elapsed(now)))
+    }
+
+    pub fn correct_chars(&self) -> usize {
+        self.correct_chars
+    }
+
+    pub fn total_typed_chars(&self) -> usize {
+        self.total_typed_chars
+    }
+
+    pub fn corrections(&self) -> usize {
+        self.corrections
+    }
+
+    pub fn wpm(&self, now: Instant) -> f64 {
+        self.chars_per_minute(self.correct_chars, now) / 5.0
+    }
+
+    /// Speed counting every character keystroke, including ones that were
+    /// later corrected.
+    pub fn raw_wpm(&self, now: Instant) -> f64 {
+        self.chars_per_minute(self.char_keystrokes(), now) / 5.0
+    }
+
+    /// Gross speed minus a word per minute for every error left
+    /// uncorrected.
+    pub fn net_wpm(&self, now: Instant) -> f64 {
+        let minutes = self.elapsed(now).as_secs_f64() / 60.0;
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        let uncorrected = (0..self.typed.len())
+            .filter(|&i| self.char_status(i) == CharStatus::Incorrect)
+            .count()
+            + self.overflow.values().map(Vec::len).sum::<usize>();
+        ((self.char_keystrokes() as f64 / 5.0 - uncorrected as f64) / minutes).max(0.0)
+    }
+
+    fn char_keystrokes(&self) -> usize {
+        self.keystrokes
+            .iter()
+            .filter(|k| matches!(k.action, KeyAction::Char { .. }))
+            .count()
+    }
+
+    /// Per-word totals, in order of first appearance, for every word that
+    /// was typed into. Each key press counts the time since the previous
+    /// one, so the first character of a word includes reaching it from the
+    /// space before; pauses longer than [`MAX_KEY_GAP`] are left out.
+    pub fn word_stats(&self) -> Vec<WordStat> {
+        // Map each target position to the word it belongs to.
+        let mut word_at = vec![None; self.target.len()];
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for (i, grapheme) in self.target.iter().enumerate() {
+            if is_whitespace(grapheme) {
+                continue;
+            }
+            match spans.last_mut() {
+                Some((_, end)) if *end == i => *end = i + 1,
+                _ => spans.push((i, i + 1)),
+            }
+            word_at[i] = Some(spans.len() - 1);
+        }
+
+        let mut stats: Vec<WordStat> = Vec::new();
+        let mut previous_at = None;
+        for keystroke in &self.keystrokes {
+            if let KeyAction::Char { index, .. } = keystroke.action {
+                if let Some(span) = word_at.get(index).copied().flatten() {
+                    let (start, end) = spans[span];
+                    let word = self.target[start..end].concat();
+                    let position = match stats.iter().position(|s| s.word == word) {
+                        Some(position) => position,
+                        None => {
+                            stats.push(WordStat {
+                                word,
+                                chars: 0,
+                                errors: 0,
+                                time: Duration::ZERO,
+                            });
+                            stats.len() - 1
+                        }
+                    };
+                    let stat = &mut stats[position];
+                    stat.chars += 1;
+                    if !keystroke.is_correct() {
+                        stat.errors += 1;
+                    }
+                    if let Some(gap) = previous_at.map(|at| keystroke.at.saturating_sub(at)) {
+                        if gap <= MAX_KEY_GAP {
+                            stat.time += gap;
+                        }
+                    }
+                }
+            }
+            previous_at = Some(keystroke.at);
+        }
+        stats
+    }
+
+    /// Words with at least one mistyped character, even if it was later
+    /// corrected, in order of first appearance.
+    pub fn missed_words(&self) -> Vec<String> {
+        self.word_stats()
+            .into_iter()
+            .filter(|stat| stat.errors > 0)
+            .map(|stat| stat.word)
+            .collect()
+    }
+
+    /// One sample per whole second of the test. A trailing part-second
+    /// shorter than half a second is left out as too noisy to be useful.
+    pub fn speed_samples(&self, now: Instant) -> Vec<SpeedSample> {
+        let elapsed = self.elapsed(now).as_secs_f64();
+        let seconds = elapsed.ceil() as usize;
+        // A test that ended the instant it started, e.g. one whose only key
+        // finished it, has no seconds to bucket keystrokes into.
+        if seconds == 0 {
+            return Vec::new();
+        }
+        let mut chars = vec![0u32; seconds];
+        let mut errors = vec![0u32; seconds];
+        let mut correct = vec![0u32; seconds];
+
+        for keystroke in &self.keystrokes {
+            if !matches!(keystroke.action, KeyAction::Char { .. }) {
+                continue;
+            }
+            let second = (keystroke.at.as_secs() as usize).min(seconds.saturating_sub(1));
+            chars[second] += 1;
+            if keystroke.is_correct() {
+                correct[second] += 1;
+            } else {
+                errors[second] += 1;
+            }
+        }
+
+        let mut samples = Vec::new();
+        let mut correct_so_far = 0;
+        for second in 0..seconds {
+            let length = (elapsed - second as f64).min(1.0);
+            correct_so_far += correct[second];
+            if length < 0.5 {
+                break;
+            }
+            let minutes_so_far = (second as f64 + length) / 60.0;
+            samples.push(SpeedSample {
+                wpm: correct_so_far as f64 / 5.0 / minutes_so_far,
+                raw_wpm: chars[second] as f64 / 5.0 / (length / 60.0),
+                errors: errors[second],
+            });
+        }
+        samples
+    }
+
+    /// How steady the raw speed was from second to second, as a percentage
+    /// where 100 means perfectly even. Based on the coefficient of
+    /// variation of the per-second raw speed.
+    pub fn consistency(&self, now: Instant) -> Option<f64> {
+        let speeds: Vec<f64> = self.speed_samples(now).iter().map(|s| s.raw_wpm).collect();
+        if speeds.len() < 2 {
+            return None;
+        }
+        let mean = speeds.iter().sum::<f64>() / speeds.len() as f64;
+        if mean <= 0.0 {
+            return Some(0.0);
+        }
+        let variance = speeds.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / speeds.len() as f64;
+        let cv = variance.sqrt() / mean;
+        Some(((1.0 - cv) * 100.0).clamp(0.0, 100.0))
+    }
+
+    fn chars_per_minute(&self, chars: usize, now: Instant) -> f64 {
+        let minutes = self.elapsed(now).as_secs_f64() / 60.0;
+        if minutes > 0.0 {
+            chars as f64 / minutes
+        } else {
+            0.0
+        }
+    }
+
+    /// Accuracy as a percentage, or `None` if nothing has been typed yet.
+    pub fn accuracy(&self) -> Option<f64> {
+        if self.total_typed_chars > 0 {
+            Some(self.correct_chars as f64 / self.total_typed_chars as f64 * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_samples_are_empty_when_no_time_has_passed() {
+        let start = Instant::now();
+        let mut session = TypingSession::from_text("a", TestMode::Text(1));
+        session.type_char('a', start);
+
+        assert!(session.is_finished(start));
+        assert!(session.speed_samples(start).is_empty());
+        assert_eq!(session.consistency(start), None);
+    }
+
+    fn type_str(session: &mut TypingSession, text: &str, at: Instant) {
+        for c in text.chars() {
+            session.type_char(c, at);
+        }
+    }
+
+    fn words(text: &str) -> TypingSession {
+        let words = text_to_words(text);
+        let count = words.len() as u32;
+        TypingSession::new(words, TestMode::Words(count))
+    }
+
+    #[test]
+    fn normalize_code_expands_tabs_and_drops_blank_edges() {
+        assert_eq!(normalize_code("\n\n\tfn main() {}  \n\n"), "fn main() {}");
+        assert_eq!(normalize_code("a\n\tb"), "a\n    b");
+        assert_eq!(normalize_code(" \n\t\n"), "");
+    }
+
+    #[test]
+    fn normalize_code_removes_only_the_shared_indent() {
+        assert_eq!(
+            normalize_code("    if x {\n\n        y();\n    }"),
+            "if x {\n\n    y();\n}"
+        );
+        assert_eq!(normalize_code("  a\nb"), "  a\nb");
+    }
+
+    #[test]
+    fn normalize_code_measures_the_indent_in_characters() {
+        assert_eq!(
+            normalize_code("\u{a0}\u{a0}a\n\u{a0}\u{a0}\u{a0}b"),
+            "a\n\u{a0}b"
+        );
+        assert_eq!(normalize_code("\u{3000}a\n\u{3000}b"), "a\nb");
+        assert_eq!(normalize_code("  a\n\u{3000}b"), " a\nb");
+    }
+
+    #[test]
+    fn typed_characters_are_scored_against_the_target() {
+        let start = Instant::now();
+        let mut session = words("ab cd");
+        type_str(&mut session, "ax", start);
+
+        assert_eq!(session.char_status(0), CharStatus::Correct);
+        assert_eq!(session.char_status(1), CharStatus::Incorrect);
+        assert_eq!(session.char_status(2), CharStatus::Pending);
+        assert_eq!(session.caret(), 2);
+        assert_eq!(session.correct_chars(), 1);
+        assert_eq!(session.total_typed_chars(), 2);
+        assert_eq!(session.accuracy(), Some(50.0));
+    }
+
+    #[test]
+    fn backspace_takes_back_the_last_character() {
+        let start = Instant::now();
+        let mut session = words("ab cd");
+        session.backspace(start);
+        assert!(!session.is_started());
+        assert!(session.keystrokes().is_empty());
+
+        type_str(&mut session, "ax", start);
+        session.backspace(start);
+        assert_eq!(session.caret(), 1);
+        assert_eq!(session.correct_chars(), 1);
+        assert_eq!(session.total_typed_chars(), 1);
+        assert_eq!(session.accuracy(), Some(100.0));
+
+        session.backspace(start);
+        assert_eq!(session.correct_chars(), 0);
+        assert_eq!(session.total_typed_chars(), 0);
+        assert_eq!(session.accuracy(), None);
+        assert_eq!(session.keystrokes().len(), 4);
+    }
+
+    #[test]
+    fn wpm_counts_five_correct_characters_as_a_word() {
+        let start = Instant::now();
+        let mut session = words("hello world again");
+        type_str(&mut session, "hello worl", start);
+
+        assert_eq!(session.wpm(start), 0.0);
+        assert_eq!(session.wpm(start + Duration::from_secs(12)), 10.0);
+        assert_eq!(session.raw_wpm(start + Duration::from_secs(12)), 10.0);
+    }
+
+    #[test]
+    fn finish_freezes_the_elapsed_time() {
+        let start = Instant::now();
+        let mut session = words("hello world");
+        assert!(!session.is_finished(start));
+        session.type_char('h', start);
+        session.finish(start + Duration::from_secs(10));
+
+        assert!(session.is_finished(start + Duration::from_secs(10)));
+        assert_eq!(
+            session.elapsed(start + Duration::from_secs(60)),
+            Duration::from_secs(10)
+        );
+        session.type_char('e', start + Duration::from_secs(11));
+        assert_eq!(session.caret(), 1);
+        assert!(!session.completed());
+    }
+
+    #[test]
+    fn word_count_test_ends_once_the_target_is_typed() {
+        let start = Instant::now();
+        let mut session = words("ab cd");
+        type_str(&mut session, "ab c", start);
+        assert!(!session.is_finished(start + Duration::from_secs(60)));
+        assert_eq!(session.words_typed(), 1);
+
+        session.type_char('x', start + Duration::from_secs(2));
+        assert!(session.is_finished(start + Duration::from_secs(60)));
+        assert!(session.completed());
+        assert_eq!(
+            session.elapsed(start + Duration::from_secs(60)),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn timed_test_ends_when_the_time_runs_out() {
+        let start = Instant::now();
+        let pool = vec!["ab".to_string(), "cd".to_string()];
+        let mut session = TypingSession::timed(pool, 30, WordModifiers::default());
+        assert!(session.has_word_pool());
+        session.extend_from_pool(10);
+        assert_eq!(session.words().len(), 10);
+
+        let first = session.target()[0].chars().next().unwrap();
+        session.type_char(first, start);
+        let almost = start + Duration::from_secs(29);
+        assert!(!session.is_finished(almost));
+        assert_eq!(session.time_left(almost), Some(Duration::from_secs(1)));
+
+        let late = start + Duration::from_secs(45);
+        assert!(session.is_finished(late));
+        session.finish(late);
+        assert_eq!(session.elapsed(late), Duration::from_secs(30));
+        assert!(session.completed());
+    }
+
+    fn with_rules(text: &str, rules: Rules) -> TypingSession {
+        let mut session = TypingSession::from_text(text, TestMode::Text(1));
+        session.set_rules(rules);
+        session
+    }
+
+    #[test]
+    fn sudden_death_fails_on_a_wrong_first_key() {
+        let start = Instant::now();
+        let mut session = with_rules(
+            "hello",
+            Rules {
+                sudden_death: true,
+                ..Rules::default()
+            },
+        );
+        session.type_char('x', start);
+
+        assert!(session.is_finished(start));
+        assert_eq!(session.failure(), Some(Failure::Mistake));
+        assert!(!session.completed());
+        assert!(session.speed_samples(start).is_empty());
+    }
+
+    #[test]
+    fn min_accuracy_waits_for_enough_characters() {
+        let start = Instant::now();
+        let mut session = with_rules(
+            "the quick brown fox",
+            Rules {
+                min_accuracy: Some(95),
+                ..Rules::default()
+            },
+        );
+        session.type_char('x', start);
+        session.check_limits(start);
+        assert_eq!(session.failure(), None);
+
+        for c in "he quick".chars() {
+            session.type_char(c, start);
+        }
+        session.check_limits(start);
+        assert_eq!(session.failure(), None);
+
+        session.type_char(' ', start);
+        session.check_limits(start);
+        assert!(matches!(
+            session.failure(),
+            Some(Failure::Accuracy { min: 95, .. })
+        ));
+        assert!(session.is_finished(start));
+    }
+
+    #[test]
+    fn min_wpm_waits_for_the_grace_period() {
+        let start = Instant::now();
+        let mut session = with_rules(
+            "the quick brown fox",
+            Rules {
+                min_wpm: Some(40),
+                ..Rules::default()
+            },
+        );
+        session.type_char('t', start);
+        session.check_limits(start + MIN_WPM_GRACE - Duration::from_millis(1));
+        assert_eq!(session.failure(), None);
+
+        session.check_limits(start + MIN_WPM_GRACE);
+        assert!(matches!(
+            session.failure(),
+            Some(Failure::Speed { min: 40, .. })
+        ));
+    }
+
+    fn word_mode(text: &str) -> TypingSession {
+        with_rules(
+            text,
+            Rules {
+                error_mode: ErrorMode::Word,
+                ..Rules::default()
+            },
+        )
+    }
+
+    #[test]
+    fn word_mode_space_mid_word_skips_to_the_next_word() {
+        let start = Instant::now();
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "he ", start);
+
+        assert_eq!(session.caret(), 6);
+        assert_eq!(session.typed()[2], "");
+        for i in 2..5 {
+            assert_eq!(session.char_status(i), CharStatus::Incorrect);
+        }
+        assert_eq!(session.char_status(5), CharStatus::Correct);
+        assert_eq!(session.total_typed_chars(), 6);
+        assert_eq!(session.correct_chars(), 3);
+        assert_eq!(session.missed_words(), vec!["hello".to_string()]);
+
+        // A space before any of the next word is typed does nothing.
+        session.type_char(' ', start);
+        assert_eq!(session.caret(), 6);
+    }
+
+    #[test]
+    fn word_mode_keeps_extra_letters_past_the_end_of_a_word() {
+        let start = Instant::now();
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "helloxyz", start);
+
+        assert_eq!(session.caret(), 5);
+        assert_eq!(session.overflow(5), ["x", "y", "z"]);
+        assert_eq!(session.total_typed_chars(), 8);
+        assert_eq!(session.missed_words(), vec!["hello".to_string()]);
+
+        type_str(&mut session, &"x".repeat(20), start);
+        assert_eq!(session.overflow(5).len(), MAX_OVERFLOW);
+
+        session.backspace(start);
+        assert_eq!(session.overflow(5).len(), MAX_OVERFLOW - 1);
+        assert_eq!(session.caret(), 5);
+    }
+
+    #[test]
+    fn word_mode_backspace_stays_out_of_a_correct_word() {
+        let start = Instant::now();
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "hello ", start);
+        let logged = session.keystrokes().len();
+
+        session.backspace(start);
+        assert_eq!(session.caret(), 6);
+        assert_eq!(session.keystrokes().len(), logged);
+    }
+
+    #[test]
+    fn word_mode_backspace_goes_back_into_a_word_with_errors() {
+        let start = Instant::now();
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "hxllo ", start);
+        session.backspace(start);
+        assert_eq!(session.caret(), 5);
+
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "helloo ", start);
+        session.backspace(start);
+        assert_eq!(session.caret(), 5);
+        session.backspace(start);
+        assert!(session.overflow(5).is_empty());
+        assert_eq!(session.caret(), 5);
+
+        // The letters skipped by an early space come back with it.
+        let mut session = word_mode("hello world");
+        type_str(&mut session, "he ", start);
+        session.backspace(start);
+        assert_eq!(session.caret(), 2);
+        assert_eq!(session.total_typed_chars(), 2);
+        assert_eq!(session.accuracy(), Some(100.0));
+    }
+
+    fn strict(text: &str, strictness: Strictness) -> TypingSession {
+        with_rules(
+            text,
+            Rules {
+                strict: Some(strictness),
+                ..Rules::default()
+            },
+        )
+    }
+
+    #[test]
+    fn strict_letter_refuses_wrong_keys() {
+        let start = Instant::now();
+        let mut session = strict("ab", Strictness::Letter);
+        type_str(&mut session, "xa", start);
+
+        assert_eq!(session.caret(), 1);
+        assert_eq!(session.char_status(0), CharStatus::Correct);
+        assert_eq!(session.keystrokes().len(), 2);
+        assert!(!session.keystrokes()[0].is_correct());
+        assert_eq!(session.total_typed_chars(), 2);
+        assert_eq!(session.correct_chars(), 1);
+        assert_eq!(session.corrections(), 1);
+        assert_eq!(session.accuracy(), Some(50.0));
+    }
+
+    #[test]
+    fn strict_letter_waits_for_a_half_typed_grapheme() {
+        let start = Instant::now();
+        let mut session = strict("éa", Strictness::Letter);
+        // A bare `e` is the start of `é` spelled with a combining accent.
+        session.type_char('e', start);
+        assert_eq!(session.caret(), 1);
+        assert_eq!(session.char_status(0), CharStatus::Incorrect);
+
+        session.type_char('a', start);
+        assert_eq!(session.caret(), 1);
+        assert_eq!(session.corrections(), 1);
+
+        session.type_char('\u{301}', start);
+        assert_eq!(session.char_status(0), CharStatus::Correct);
+        session.type_char('a', start);
+        assert!(session.completed());
+    }
+
+    #[test]
+    fn strict_word_blocks_the_space_after_a_word_with_errors() {
+        let start = Instant::now();
+        let mut session = strict("hello world", Strictness::Word);
+        type_str(&mut session, "hxllo", start);
+        let logged = session.keystrokes().len();
+
+        // Keys that would move past the word are dropped without a trace.
+        type_str(&mut session, " w", start);
+        assert_eq!(session.caret(), 5);
+        assert_eq!(session.keystrokes().len(), logged);
+        assert_eq!(session.total_typed_chars(), 5);
+
+        for _ in 0..4 {
+            session.backspace(start);
+        }
+        assert_eq!(session.corrections(), 1);
+        type_str(&mut session, "ello w", start);
+        assert_eq!(session.caret(), 7);
+    }
+
+    #[test]
+    fn strict_word_wont_finish_with_errors_in_the_last_word() {
+        let start = Instant::now();
+        let mut session = strict("hello", Strictness::Word);
+        type_str(&mut session, "hxllo", start);
+        assert!(!session.is_finished(start));
+        assert!(!session.completed());
+
+        // Past the end only backspace gets anywhere.
+        let logged = session.keystrokes().len();
+        session.type_char('o', start);
+        assert_eq!(session.caret(), 5);
+        assert_eq!(session.keystrokes().len(), logged);
+
+        for _ in 0..4 {
+            session.backspace(start);
+        }
+        type_str(&mut session, "ello", start);
+        assert!(session.is_finished(start));
+        assert!(session.completed());
+    }
+
+    #[test]
+    fn strict_word_blocks_the_word_after_a_mistyped_space() {
+        let start = Instant::now();
+        let mut session = strict("hello world", Strictness::Word);
+        type_str(&mut session, "hellox", start);
+        assert_eq!(session.char_status(5), CharStatus::Incorrect);
+
+        session.type_char('w', start);
+        assert_eq!(session.caret(), 6);
+
+        session.backspace(start);
+        assert_eq!(session.corrections(), 1);
+        type_str(&mut session, " w", start);
+        assert_eq!(session.caret(), 7);
+    }
+}
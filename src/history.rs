@@ -0,0 +1,383 @@
+use serde_json::{json, Value};
+use std::io;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::game::{SpeedSample, TypingSession, WordModifiers};
+use crate::storage::{append_json_line, read_json_lines};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub timestamp: u64,
+    pub language: String,
+    pub mode: String,
+    pub limit: u32,
+    pub duration: f64,
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    pub net_wpm: f64,
+    pub accuracy: f64,
+    pub consistency: Option<f64>,
+    pub correct_chars: usize,
+    pub total_chars: usize,
+    pub samples: Vec<SpeedSample>,
+    /// Punctuation and numbers mixed into random words.
+    pub modifiers: WordModifiers,
+    /// False for a test ended early, which is kept but left out of
+    /// summaries and personal bests.
+    pub completed: bool,
+    /// Why the test failed, for one ended by a sudden death or minimum
+    /// accuracy or speed rule.
+    pub failure: Option<String>,
+    /// The kind of drill, `ngrams` or `retry`, for practice that isn't a
+    /// test; kept but left out of summaries and personal bests.
+    pub drill: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn from_session(session: &TypingSession, language: &str, now: Instant) -> Self {
+        Self {
+            timestamp: unix_now(),
+            language: language.to_string(),
+            mode: session.mode().name().to_string(),
+            limit: session.mode().limit(),
+            duration: session.elapsed(now).as_secs_f64(),
+            wpm: session.wpm(now),
+            raw_wpm: session.raw_wpm(now),
+            net_wpm: session.net_wpm(now),
+            accuracy: session.accuracy().unwrap_or(0.0),
+            consistency: session.consistency(now),
+            correct_chars: session.correct_chars(),
+            total_chars: session.total_typed_chars(),
+            samples: session.speed_samples(now),
+            modifiers: session.word_modifiers(),
+            completed: session.completed(),
+            failure: session.failure().map(|f| f.reason()),
+            drill: session.drill().map(|d| d.name().to_string()),
+        }
+    }
+
+    /// The kind of drill, e.g. `retry drill`, along with `failed` or `ended
+    /// early` for a test that didn't run its course; empty for a completed
+    /// test.
+    pub fn status(&self) -> String {
+        let outcome = if self.failure.is_some() {
+            Some("failed")
+        } else if self.completed {
+            None
+        } else {
+            Some("ended early")
+        };
+        let drill = self.drill.as_ref().map(|d| format!("{} drill", d));
+        drill
+            .into_iter()
+            .chain(outcome.map(str::to_string))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Short label for the mode, e.g. `time 30` or `time 30 +punct`.
+    pub fn mode_label(&self) -> String {
+        mode_label(&self.mode, self.limit, self.modifiers)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "timestamp": self.timestamp,
+            "language": self.language,
+            "mode": self.mode,
+            "limit": self.limit,
+            "duration": self.duration,
+            "wpm": self.wpm,
+            "raw_wpm": self.raw_wpm,
+            "net_wpm": self.net_wpm,
+            "accuracy": self.accuracy,
+            "consistency": self.consistency,
+            "correct_chars": self.correct_chars,
+            "total_chars": self.total_chars,
+            "wpm_samples": self.samples.iter().map(|s| round1(s.wpm)).collect::<Vec<_>>(),
+            "raw_samples": self.samples.iter().map(|s| round1(s.raw_wpm)).collect::<Vec<_>>(),
+            "error_samples": self.samples.iter().map(|s| s.errors).collect::<Vec<_>>(),
+            "punctuation": self.modifiers.punctuation,
+            "numbers": self.modifiers.numbers,
+            "completed": self.completed,
+            "failure": self.failure,
+            "drill": self.drill,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            timestamp: value["timestamp"].as_u64()?,
+            language: value["language"].as_str()?.to_string(),
+            mode: value["mode"].as_str().unwrap_or("time").to_string(),
+            limit: value["limit"].as_u64().unwrap_or(0) as u32,
+            duration: value["duration"].as_f64().unwrap_or(0.0),
+            wpm: value["wpm"].as_f64()?,
+            raw_wpm: value["raw_wpm"].as_f64().unwrap_or(0.0),
+            net_wpm: value["net_wpm"].as_f64().unwrap_or(0.0),
+            accuracy: value["accuracy"].as_f64().unwrap_or(0.0),
+            consistency: value["consistency"].as_f64(),
+            correct_chars: value["correct_chars"].as_u64().unwrap_or(0) as usize,
+            total_chars: value["total_chars"].as_u64().unwrap_or(0) as usize,
+            samples: samples_from_json(value),
+            modifiers: modifiers_from_json(value),
+            // Entries from before this was recorded were all kept as results.
+            completed: value["completed"].as_bool().unwrap_or(true),
+            failure: value["failure"].as_str().map(str::to_string),
+            drill: value["drill"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// `time 30`, with any word modifiers after it, e.g. `time 30 +punct +num`.
+pub fn mode_label(mode: &str, limit: u32, modifiers: WordModifiers) -> String {
+    if modifiers.is_empty() {
+        format!("{} {}", mode, limit)
+    } else {
+        format!("{} {} {}", mode, limit, modifiers.label())
+    }
+}
+
+/// Reads the `punctuation` and `numbers` flags stored alongside a result.
+pub fn modifiers_from_json(value: &Value) -> WordModifiers {
+    WordModifiers {
+        punctuation: value["punctuation"].as_bool().unwrap_or(false),
+        numbers: value["numbers"].as_bool().unwrap_or(false),
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+fn samples_from_json(value: &Value) -> Vec<SpeedSample> {
+    let column = |key: &str| value[key].as_array().cloned().unwrap_or_default();
+    let wpm = column("wpm_samples");
+    let raw = column("raw_samples");
+    let errors = column("error_samples");
+
+    wpm.iter()
+        .enumerate()
+        .map(|(i, w)| SpeedSample {
+            wpm: w.as_f64().unwrap_or(0.0),
+            raw_wpm: raw.get(i).and_then(Value::as_f64).unwrap_or(0.0),
+            errors: errors.get(i).and_then(Value::as_u64).unwrap_or(0) as u32,
+        })
+        .collect()
+}
+
+pub fn append_entry(entry: &HistoryEntry) -> io::Result<()> {
+    append_json_line(HISTORY_FILE, &entry.to_json())
+}
+
+pub fn load_entries() -> io::Result<Vec<HistoryEntry>> {
+    Ok(read_json_lines(HISTORY_FILE)?
+        .iter()
+        .filter_map(HistoryEntry::from_json)
+        .collect())
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub lang: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        if let Some(lang) = &self.lang {
+            if &entry.language != lang {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if entry.timestamp < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if entry.timestamp >= until {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub fn print_entries(entries: &[&HistoryEntry]) {
+    println!(
+        "{:<16}  {:<12}  {:<20}  {:>5}  {:>5}  {:>5}  {:>5}  {:>5}  {:>6}  status",
+        "date", "language", "mode", "wpm", "raw", "net", "acc", "cons", "time"
+    );
+    for entry in entries {
+        let consistency = entry
+            .consistency
+            .map_or("-".to_string(), |c| format!("{:.0}%", c));
+        println!(
+            "{:<16}  {:<12}  {:<20}  {:>5.0}  {:>5.0}  {:>5.0}  {:>4.0}%  {:>5}  {:>5.0}s  {}",
+            format_timestamp(entry.timestamp),
+            entry.language,
+            entry.mode_label(),
+            entry.wpm,
+            entry.raw_wpm,
+            entry.net_wpm,
+            entry.accuracy,
+            consistency,
+            entry.duration,
+            entry.status()
+        );
+    }
+}
+
+/// Averages and bests over the completed tests among `entries`, drills
+/// aside.
+pub fn print_summary(entries: &[&HistoryEntry]) {
+    let drills = entries.iter().filter(|e| e.drill.is_some()).count();
+    let entries: Vec<&HistoryEntry> = entries.iter().copied().filter(|e| e.drill.is_none()).collect();
+    let failed = entries.iter().filter(|e| e.failure.is_some()).count();
+    let ended_early = entries.iter().filter(|e| !e.completed).count() - failed;
+    let entries: Vec<&HistoryEntry> = entries.iter().copied().filter(|e| e.completed).collect();
+    if entries.is_empty() {
+        println!("No completed tests to summarise.");
+        return;
+    }
+    let count = entries.len() as f64;
+    let avg = |f: fn(&HistoryEntry) -> f64| entries.iter().map(|e| f(e)).sum::<f64>() / count;
+    let best_wpm = entries.iter().map(|e| e.wpm).fold(0.0, f64::max);
+    let total_time: f64 = entries.iter().map(|e| e.duration).sum();
+
+    println!("tests:        {}", entries.len());
+    if ended_early > 0 {
+        println!("ended early:  {} (not counted)", ended_early);
+    }
+    if failed > 0 {
+        println!("failed:       {} (not counted)", failed);
+    }
+    if drills > 0 {
+        println!("drills:       {} (not counted)", drills);
+    }
+    println!("time typing:  {:.0}s", total_time);
+    println!("average wpm:  {:.1}", avg(|e| e.wpm));
+    println!("average raw:  {:.1}", avg(|e| e.raw_wpm));
+    println!("average net:  {:.1}", avg(|e| e.net_wpm));
+    println!("average acc:  {:.1}%", avg(|e| e.accuracy));
+    let consistencies: Vec<f64> = entries.iter().filter_map(|e| e.consistency).collect();
+    if !consistencies.is_empty() {
+        let mean = consistencies.iter().sum::<f64>() / consistencies.len() as f64;
+        println!("average cons: {:.1}%", mean);
+    }
+    println!("best wpm:     {:.0}", best_wpm);
+
+    // Compare the most recent ten tests against the ten before them.
+    if entries.len() >= 20 {
+        let recent = &entries[entries.len() - 10..];
+        let previous = &entries[entries.len() - 20..entries.len() - 10];
+        let mean = |es: &[&HistoryEntry]| es.iter().map(|e| e.wpm).sum::<f64>() / es.len() as f64;
+        println!("last 10 vs previous 10: {:+.1} wpm", mean(recent) - mean(previous));
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parses a `YYYY-MM-DD` date into a unix timestamp at midnight UTC.
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // A day past the end of the month rolls over into the next one.
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    u64::try_from(days * 86_400).ok()
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs_of_day = timestamp % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60
+    )
+}
+
+// Conversions between days since the unix epoch and proleptic Gregorian
+// dates, following Howard Hinnant's `days_from_civil` algorithms.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_gives_midnight_utc() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-03-01"), Some(1_709_251_200));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+    }
+
+    #[test]
+    fn parse_date_rejects_days_past_the_end_of_the_month() {
+        assert_eq!(parse_date("2024-02-30"), None);
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2024-04-31"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-01-00"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("2024-01"), None);
+    }
+
+    #[test]
+    fn format_timestamp_shows_the_utc_date_and_time() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_709_164_800 + 13 * 3600 + 5 * 60 + 59), "2024-02-29 13:05");
+    }
+
+    #[test]
+    fn civil_days_round_trip() {
+        for days in -800_000..800_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(days_from_civil(2000, 2, 29)), (2000, 2, 29));
+    }
+}
@@ -0,0 +1,154 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Spacing accents that keyboard layouts with dead keys send on their own
+/// when the terminal doesn't compose them, with the combining mark each
+/// one stands for.
+const DEAD_KEYS: &[(char, char)] = &[
+    ('`', '\u{300}'),
+    ('´', '\u{301}'),
+    ('^', '\u{302}'),
+    ('~', '\u{303}'),
+    ('¯', '\u{304}'),
+    ('˘', '\u{306}'),
+    ('˙', '\u{307}'),
+    ('¨', '\u{308}'),
+    ('˚', '\u{30A}'),
+    ('˝', '\u{30B}'),
+    ('ˇ', '\u{30C}'),
+    ('¸', '\u{327}'),
+    ('˛', '\u{328}'),
+];
+
+/// Composes dead keys the terminal passed through as separate characters,
+/// so `^` then `e` types `ê`.
+///
+/// An accent is only held back when it isn't itself the character expected
+/// next, so code and text containing `^` or `~` still type as usual.
+#[derive(Debug, Default)]
+pub struct Composer {
+    pending: Option<char>,
+}
+
+impl Composer {
+    /// Feeds one character from the terminal, given the grapheme expected at
+    /// the caret, and returns the characters to type: none while a dead key
+    /// waits for its letter, otherwise the composed letter or whatever was
+    /// typed.
+    pub fn feed(&mut self, c: char, expected: Option<&str>) -> Vec<char> {
+        if let Some(accent) = self.pending.take() {
+            if let Some(composed) = compose(accent, c) {
+                return vec![composed];
+            }
+            // As with a real dead key, a space gives the accent itself.
+            if c == ' ' {
+                return vec![accent];
+            }
+            return vec![accent, c];
+        }
+
+        if combining_mark(c).is_some() && !expected.is_some_and(|e| e.starts_with(c)) {
+            self.pending = Some(c);
+            return Vec::new();
+        }
+        vec![c]
+    }
+
+    /// The dead key waiting for a letter, if any.
+    pub fn pending(&self) -> Option<char> {
+        self.pending
+    }
+
+    /// Drops a waiting dead key.
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Gives up waiting for a letter and returns the dead key itself, to be
+    /// typed before a key that doesn't go through the composer.
+    pub fn flush(&mut self) -> Option<char> {
+        self.pending.take()
+    }
+}
+
+fn combining_mark(accent: char) -> Option<char> {
+    DEAD_KEYS
+        .iter()
+        .find(|&&(key, _)| key == accent)
+        .map(|&(_, mark)| mark)
+}
+
+/// The precomposed letter for `base` with `accent`, if Unicode has one.
+fn compose(accent: char, base: char) -> Option<char> {
+    let mark = combining_mark(accent)?;
+    let mut composed = [base, mark].into_iter().nfc();
+    match (composed.next(), composed.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Characters to type for text pasted into the terminal. Line endings
+/// become line breaks in code and spaces anywhere else.
+pub fn pasted_chars(text: &str, keep_newlines: bool) -> Vec<char> {
+    text.chars()
+        .filter(|&c| c != '\r')
+        .map(|c| if c == '\n' && !keep_newlines { ' ' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compose_finds_precomposed_letters() {
+        assert_eq!(compose('^', 'e'), Some('ê'));
+        assert_eq!(compose('¨', 'U'), Some('Ü'));
+        assert_eq!(compose('^', 'q'), None);
+        assert_eq!(compose('x', 'e'), None);
+    }
+
+    #[test]
+    fn dead_key_waits_for_its_letter() {
+        let mut composer = Composer::default();
+        assert!(composer.feed('^', Some("ê")).is_empty());
+        assert_eq!(composer.pending(), Some('^'));
+        assert_eq!(composer.feed('e', Some("ê")), vec!['ê']);
+        assert_eq!(composer.pending(), None);
+    }
+
+    #[test]
+    fn dead_key_without_a_composed_letter_types_both() {
+        let mut composer = Composer::default();
+        composer.feed('~', Some("q"));
+        assert_eq!(composer.feed('q', Some("q")), vec!['~', 'q']);
+
+        composer.feed('~', Some("a"));
+        assert_eq!(composer.feed(' ', Some("a")), vec!['~']);
+    }
+
+    #[test]
+    fn expected_accent_is_typed_straight_away() {
+        let mut composer = Composer::default();
+        assert_eq!(composer.feed('^', Some("^")), vec!['^']);
+        assert_eq!(composer.pending(), None);
+    }
+
+    #[test]
+    fn flush_and_cancel_clear_the_dead_key() {
+        let mut composer = Composer::default();
+        composer.feed('~', Some("\n"));
+        assert_eq!(composer.flush(), Some('~'));
+        assert_eq!(composer.flush(), None);
+
+        composer.feed('~', Some("a"));
+        composer.cancel();
+        assert_eq!(composer.feed('a', Some("a")), vec!['a']);
+    }
+
+    #[test]
+    fn pasted_line_endings_follow_the_mode() {
+        assert_eq!(pasted_chars("a\r\nb", false), vec!['a', ' ', 'b']);
+        assert_eq!(pasted_chars("a\r\nb", true), vec!['a', '\n', 'b']);
+    }
+}
@@ -0,0 +1,398 @@
+use crossterm::{
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    ExecutableCommand,
+};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::io::{self, stdout};
+use std::str::FromStr;
+
+use crate::game::{KeyAction, TypingSession, MAX_KEY_GAP};
+use crate::storage::{read_json, write_json};
+
+const KEY_STATS_FILE: &str = "key_stats.json";
+const BIGRAM_STATS_FILE: &str = "bigram_stats.json";
+
+/// Keys and bigrams seen fewer times than this are too noisy to practise on.
+const MIN_SAMPLES: u64 = 5;
+
+/// Accuracy and latency totals for one expected character.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyStat {
+    pub hits: u64,
+    pub errors: u64,
+    pub latency_ms: u64,
+    pub timed_hits: u64,
+}
+
+impl KeyStat {
+    pub fn error_rate(&self) -> f64 {
+        if self.hits == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.hits as f64 * 100.0
+    }
+
+    pub fn mean_latency(&self) -> Option<f64> {
+        if self.timed_hits == 0 {
+            return None;
+        }
+        Some(self.latency_ms as f64 / self.timed_hits as f64)
+    }
+
+    fn merge(&mut self, other: &KeyStat) {
+        self.hits += other.hits;
+        self.errors += other.errors;
+        self.latency_ms += other.latency_ms;
+        self.timed_hits += other.timed_hits;
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "hits": self.hits,
+            "errors": self.errors,
+            "latency_ms": self.latency_ms,
+            "timed_hits": self.timed_hits,
+        })
+    }
+
+    fn from_json(value: &Value) -> Self {
+        let field = |key: &str| value[key].as_u64().unwrap_or(0);
+        Self {
+            hits: field("hits"),
+            errors: field("errors"),
+            latency_ms: field("latency_ms"),
+            timed_hits: field("timed_hits"),
+        }
+    }
+}
+
+pub type KeyStats = BTreeMap<char, KeyStat>;
+
+/// Totals for pairs of consecutive expected characters, e.g. `"th"`.
+pub type BigramStats = BTreeMap<String, KeyStat>;
+
+/// Totals for one test, taken from its keystroke log. Latency is the time
+/// since the previous key press, whatever that was. `key_of` picks what to
+/// count each character under, given the character expected just before it
+/// (if the previous key press was a character too).
+fn session_totals<K: Ord>(
+    session: &TypingSession,
+    key_of: impl Fn(Option<char>, char) -> Option<K>,
+) -> BTreeMap<K, KeyStat> {
+    let mut stats = BTreeMap::new();
+    let mut previous_at = None;
+    let mut previous_expected = None;
+
+    for keystroke in session.keystrokes() {
+        let expected = match keystroke.action {
+            KeyAction::Char {
+                typed, expected, ..
+            } => {
+                if let Some(key) = expected.and_then(|e| key_of(previous_expected, e)) {
+                    let stat: &mut KeyStat = stats.entry(key).or_default();
+                    stat.hits += 1;
+                    if Some(typed) != expected {
+                        stat.errors += 1;
+                    }
+                    if let Some(gap) = previous_at.map(|at| keystroke.at.saturating_sub(at)) {
+                        if gap <= MAX_KEY_GAP {
+                            stat.latency_ms += gap.as_millis() as u64;
+                            stat.timed_hits += 1;
+                        }
+                    }
+                }
+                expected
+            }
+            KeyAction::Backspace => None,
+        };
+        previous_at = Some(keystroke.at);
+        previous_expected = expected;
+    }
+    stats
+}
+
+pub fn from_session(session: &TypingSession) -> KeyStats {
+    session_totals(session, |_, expected| Some(expected))
+}
+
+pub fn bigrams_from_session(session: &TypingSession) -> BigramStats {
+    session_totals(session, |previous, expected| {
+        previous.map(|p| format!("{}{}", p, expected))
+    })
+}
+
+fn load_table<K: Ord + FromStr>(file: &str) -> io::Result<BTreeMap<String, BTreeMap<K, KeyStat>>> {
+    let mut all = BTreeMap::new();
+    if let Some(Value::Object(languages)) = read_json(file)? {
+        for (language, keys) in &languages {
+            let Some(keys) = keys.as_object() else {
+                continue;
+            };
+            let stats = keys
+                .iter()
+                .filter_map(|(key, stat)| Some((key.parse().ok()?, KeyStat::from_json(stat))))
+                .collect();
+            all.insert(language.clone(), stats);
+        }
+    }
+    Ok(all)
+}
+
+fn save_table<K: ToString>(
+    file: &str,
+    all: &BTreeMap<String, BTreeMap<K, KeyStat>>,
+) -> io::Result<()> {
+    let mut languages = Map::new();
+    for (language, stats) in all {
+        let keys: Map<String, Value> = stats
+            .iter()
+            .map(|(key, stat)| (key.to_string(), stat.to_json()))
+            .collect();
+        languages.insert(language.clone(), Value::Object(keys));
+    }
+    write_json(file, &Value::Object(languages))
+}
+
+/// Stored per-key totals, by language.
+pub fn load_stats() -> io::Result<BTreeMap<String, KeyStats>> {
+    load_table(KEY_STATS_FILE)
+}
+
+/// Stored bigram totals, by language.
+pub fn load_bigram_stats() -> io::Result<BTreeMap<String, BigramStats>> {
+    load_table(BIGRAM_STATS_FILE)
+}
+
+fn add_to_table<K: Ord + Clone + FromStr + ToString>(
+    file: &str,
+    language: &str,
+    session_stats: &BTreeMap<K, KeyStat>,
+) -> io::Result<()> {
+    if session_stats.is_empty() {
+        return Ok(());
+    }
+
+    let mut all = load_table(file)?;
+    let stats = all.entry(language.to_string()).or_default();
+    merge_into(stats, session_stats);
+    save_table(file, &all)
+}
+
+/// Adds a finished test's per-key and bigram totals to the stored ones for
+/// its language.
+pub fn record_session(session: &TypingSession, language: &str) -> io::Result<()> {
+    add_to_table(KEY_STATS_FILE, language, &from_session(session))?;
+    add_to_table(BIGRAM_STATS_FILE, language, &bigrams_from_session(session))
+}
+
+pub fn merge_into<K: Ord + Clone>(stats: &mut BTreeMap<K, KeyStat>, other: &BTreeMap<K, KeyStat>) {
+    for (key, stat) in other {
+        stats.entry(key.clone()).or_default().merge(stat);
+    }
+}
+
+/// How far each character and bigram lags behind the user's own average,
+/// used to steer word choice towards them. A score of 0 means at or better
+/// than average; 1 means twice as slow or twice as error-prone.
+#[derive(Debug, Default)]
+pub struct Weakness {
+    keys: HashMap<char, f64>,
+    bigrams: HashMap<String, f64>,
+}
+
+impl Weakness {
+    pub fn load(language: &str) -> io::Result<Self> {
+        let keys = load_stats()?.remove(language).unwrap_or_default();
+        let bigrams = load_bigram_stats()?.remove(language).unwrap_or_default();
+        Ok(Self {
+            keys: weakness_scores(&keys),
+            bigrams: weakness_scores(&bigrams),
+        })
+    }
+
+    /// Relative chance of drawing `word`: 1 plus the scores of every
+    /// character and bigram in it.
+    pub fn word_weight(&self, word: &str) -> f64 {
+        let chars: Vec<char> = word.chars().collect();
+        let keys: f64 = chars.iter().filter_map(|c| self.keys.get(c)).sum();
+        let bigrams: f64 = chars
+            .windows(2)
+            .filter_map(|pair| self.bigrams.get(&pair.iter().collect::<String>()))
+            .sum();
+        1.0 + keys + bigrams
+    }
+}
+
+fn weakness_scores<K: Clone + Eq + Hash>(stats: &BTreeMap<K, KeyStat>) -> HashMap<K, f64> {
+    let sampled: Vec<(&K, &KeyStat)> = stats
+        .iter()
+        .filter(|(_, s)| s.hits >= MIN_SAMPLES)
+        .collect();
+    let mut total = KeyStat::default();
+    for (_, stat) in &sampled {
+        total.merge(stat);
+    }
+    let (Some(mean_latency), mean_errors) = (total.mean_latency(), total.error_rate()) else {
+        return HashMap::new();
+    };
+
+    sampled
+        .into_iter()
+        .map(|(key, stat)| {
+            // Add one percentage point so a flawless average doesn't divide by zero.
+            let errors = (stat.error_rate() + 1.0) / (mean_errors + 1.0) - 1.0;
+            let latency = stat
+                .mean_latency()
+                .map_or(0.0, |ms| ms / mean_latency.max(1.0) - 1.0);
+            (key.clone(), errors.max(0.0) + latency.max(0.0))
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect()
+}
+
+fn key_label(key: char) -> String {
+    match key {
+        ' ' => "space".to_string(),
+        '\n' => "enter".to_string(),
+        '\t' => "tab".to_string(),
+        c => c.to_string(),
+    }
+}
+
+fn print_table(rows: &[(&char, &KeyStat)]) {
+    println!(
+        "{:<6}  {:>6}  {:>6}  {:>6}  {:>8}",
+        "key", "hits", "errors", "err%", "latency"
+    );
+    for (key, stat) in rows {
+        let latency = stat
+            .mean_latency()
+            .map_or("-".to_string(), |ms| format!("{:.0}ms", ms));
+        println!(
+            "{:<6}  {:>6}  {:>6}  {:>5.1}%  {:>8}",
+            key_label(**key),
+            stat.hits,
+            stat.errors,
+            stat.error_rate(),
+            latency
+        );
+    }
+}
+
+/// Prints the slowest and the most error-prone keys, ignoring keys seen
+/// fewer than `min_hits` times.
+pub fn print_keys(stats: &KeyStats, limit: usize, min_hits: u64) {
+    let mut rows: Vec<(&char, &KeyStat)> =
+        stats.iter().filter(|(_, s)| s.hits >= min_hits).collect();
+    if rows.is_empty() {
+        println!("No key has been typed at least {} times yet.", min_hits);
+        return;
+    }
+
+    rows.sort_by(|a, b| {
+        let latency = |s: &KeyStat| s.mean_latency().unwrap_or(0.0);
+        latency(b.1).total_cmp(&latency(a.1))
+    });
+    println!("slowest keys");
+    print_table(&rows[..rows.len().min(limit)]);
+
+    rows.sort_by(|a, b| {
+        b.1.error_rate()
+            .total_cmp(&a.1.error_rate())
+            .then(b.1.errors.cmp(&a.1.errors))
+    });
+    println!();
+    println!("most error-prone keys");
+    print_table(&rows[..rows.len().min(limit)]);
+}
+
+/// What the keyboard heatmap colours keys by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HeatmapMetric {
+    Errors,
+    Latency,
+}
+
+/// A US QWERTY layout, one string of unshifted and one of shifted characters
+/// per row, with the indent that staggers each row.
+const KEYBOARD_ROWS: [(&str, &str, usize); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+", 0),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|", 2),
+    ("asdfghjkl;'", "ASDFGHJKL:\"", 3),
+    ("zxcvbnm,./", "ZXCVBNM<>?", 4),
+];
+
+/// Totals for one physical key, counting both of its characters.
+fn physical_key(stats: &KeyStats, keys: &[char]) -> KeyStat {
+    let mut total = KeyStat::default();
+    for key in keys {
+        if let Some(stat) = stats.get(key) {
+            total.merge(stat);
+        }
+    }
+    total
+}
+
+fn heat_color(stat: &KeyStat, metric: HeatmapMetric, min_hits: u64) -> Color {
+    if stat.hits < min_hits.max(1) {
+        return Color::DarkGrey;
+    }
+    match metric {
+        HeatmapMetric::Errors => match (100.0 - stat.error_rate()) as u32 {
+            95..=100 => Color::Green,
+            80..=94 => Color::Yellow,
+            _ => Color::Red,
+        },
+        HeatmapMetric::Latency => match stat.mean_latency().map(|ms| ms as u32) {
+            Some(0..=199) => Color::Green,
+            Some(200..=349) => Color::Yellow,
+            Some(_) => Color::Red,
+            None => Color::DarkGrey,
+        },
+    }
+}
+
+fn print_key(label: &str, color: Color) -> io::Result<()> {
+    let mut stdout = stdout();
+    stdout.execute(SetForegroundColor(color))?;
+    stdout.execute(Print(format!("[{}]", label)))?;
+    stdout.execute(ResetColor)?;
+    Ok(())
+}
+
+/// Draws a keyboard with each key coloured by its error rate or average
+/// latency, using the same green/yellow/red scale as the accuracy display.
+pub fn print_heatmap(stats: &KeyStats, metric: HeatmapMetric, min_hits: u64) -> io::Result<()> {
+    for (row, (plain, shifted, indent)) in KEYBOARD_ROWS.iter().enumerate() {
+        print!("{}", " ".repeat(*indent));
+        for (key, shifted_key) in plain.chars().zip(shifted.chars()) {
+            let stat = physical_key(stats, &[key, shifted_key]);
+            print_key(&key.to_string(), heat_color(&stat, metric, min_hits))?;
+        }
+        if row == 2 {
+            let stat = physical_key(stats, &['\n']);
+            print_key("enter", heat_color(&stat, metric, min_hits))?;
+        }
+        println!();
+    }
+    let stat = physical_key(stats, &[' ']);
+    print!("{}", " ".repeat(14));
+    print_key("     space     ", heat_color(&stat, metric, min_hits))?;
+    println!();
+    println!();
+
+    let labels = match metric {
+        HeatmapMetric::Errors => ["under 5% errors", "5-20%", "over 20%"],
+        HeatmapMetric::Latency => ["under 200ms", "200-350ms", "over 350ms"],
+    };
+    let colors = [Color::Green, Color::Yellow, Color::Red];
+    for (label, color) in labels.iter().zip(colors) {
+        print_key(" ", color)?;
+        print!(" {}  ", label);
+    }
+    print_key(" ", Color::DarkGrey)?;
+    println!(" not enough presses");
+    Ok(())
+}
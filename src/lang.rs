@@ -1,6 +1,7 @@
 use serde_json::Value;
 use std::fs;
 use std::env;
+use unicode_segmentation::UnicodeSegmentation;
 
 const LANGUAGE_FILES: &[(&str, &str)] = &[
     ("bash", include_str!("../languages/bash.json")),
@@ -95,6 +96,92 @@ pub fn get_words(lang: &str) -> Vec<String> {
     }
 }
 
+/// Multi-line code snippets for `lang`, from the optional `snippets` array
+/// in its language file. Empty if the language has none.
+pub fn get_snippets(lang: &str) -> Vec<String> {
+    load_language_json(lang)
+        .and_then(|json| {
+            json["snippets"].as_array().map(|snippets| {
+                snippets
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum QuoteLength {
+    Short,
+    Medium,
+    Long,
+}
+
+impl QuoteLength {
+    /// Length class for quotes that don't declare one.
+    fn for_text(text: &str) -> Self {
+        match text.graphemes(true).count() {
+            0..=99 => QuoteLength::Short,
+            100..=299 => QuoteLength::Medium,
+            _ => QuoteLength::Long,
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "short" => Some(QuoteLength::Short),
+            "medium" => Some(QuoteLength::Medium),
+            "long" => Some(QuoteLength::Long),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub text: String,
+    pub source: String,
+    pub length: QuoteLength,
+}
+
+/// Attributed passages for `lang`, from the optional `quotes` array in its
+/// language file. Empty if the language has none.
+pub fn get_quotes(lang: &str) -> Vec<Quote> {
+    load_language_json(lang)
+        .and_then(|json| {
+            json["quotes"].as_array().map(|quotes| {
+                quotes
+                    .iter()
+                    .filter_map(|q| {
+                        let text = q["text"].as_str()?.to_string();
+                        let length = q["length"]
+                            .as_str()
+                            .and_then(QuoteLength::parse)
+                            .unwrap_or_else(|| QuoteLength::for_text(&text));
+                        Some(Quote {
+                            source: q["source"].as_str().unwrap_or("unknown").to_string(),
+                            text,
+                            length,
+                        })
+                    })
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn load_language_json(lang: &str) -> Option<Value> {
+    if let Some(content) = get_embedded_language_content(lang) {
+        return serde_json::from_str(content).ok();
+    }
+
+    get_language_file_paths(lang)
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
 fn get_embedded_language_content(lang: &str) -> Option<&'static str> {
     LANGUAGE_FILES
         .iter()
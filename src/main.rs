@@ -1,11 +1,36 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+mod config;
+mod frame;
 mod game;
+mod history;
+mod input;
+mod keystats;
 mod lang;
+mod pb;
+mod sound;
+mod storage;
+mod theme;
 mod tui;
 
-use crate::game::get_random_words;
-use crate::lang::{get_available_languages, get_words};
-use crate::tui::run_typing_test;
+use crate::config::load_config;
+use crate::game::{
+    common_ngrams, generate_unlimited_words, get_random_quote, get_random_snippet,
+    get_random_words, get_weighted_words, normalize_code, text_to_words, Drill, ErrorMode,
+    Rules, Strictness, TestMode, TypingSession, WordModifiers,
+};
+use crate::history::{
+    load_entries, mode_label, parse_date, print_entries, print_summary, HistoryFilter,
+};
+use crate::keystats::{
+    load_stats, merge_into, print_heatmap, print_keys, HeatmapMetric, KeyStats, Weakness,
+};
+use crate::lang::{get_available_languages, get_quotes, get_snippets, get_words, QuoteLength};
+use crate::pb::{load_bests, print_bests, reset_best};
+use crate::theme::{available_themes, load_theme};
+use crate::tui::{run_typing_test, NextTest, Screen, TuiSettings};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -22,28 +47,420 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     #[command(
-        after_help = "Examples:\n  keyzen start --lang python\n  keyzen start --duration 30 --lang javascript\n  keyzen start --list-langs"
+        after_help = "Examples:\n  keyzen start --lang python\n  keyzen start --duration 30 --lang javascript\n  keyzen start --words 50 --lang rust\n  keyzen start --punctuation --numbers\n  keyzen start --file notes.txt\n  keyzen start --code --lang rust --auto-indent\n  keyzen start --quote long\n  keyzen start --ngrams \"::,->,=>\"\n  cat snippet.rs | keyzen start --stdin\n  keyzen start --list-langs"
     )]
     Start {
-        #[arg(short, long, default_value_t = 30)]
-        duration: u32,
+        /// Test length in seconds [default: 30, or as set in the config file]
+        #[arg(short, long)]
+        duration: Option<u32>,
+
+        /// End the test after this many words instead of on a timer
+        #[arg(short, long, conflicts_with = "duration", value_parser = clap::value_parser!(u32).range(1..))]
+        words: Option<u32>,
         
-        #[arg(short, long, default_value = "english")]
-        lang: String,
+        /// Language to practise [default: english, or as set in the config file]
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        /// Type the contents of a file, in order, instead of random words
+        #[arg(short, long, conflicts_with_all = ["duration", "words", "text", "stdin"])]
+        file: Option<String>,
+
+        /// Type the given text, in order, instead of random words
+        #[arg(short, long, conflicts_with_all = ["duration", "words", "stdin"])]
+        text: Option<String>,
+
+        /// Type text piped in on standard input, in order
+        #[arg(long, conflicts_with_all = ["duration", "words"])]
+        stdin: bool,
         
+        /// Type a code snippet with its line breaks and indentation; also
+        /// keeps the layout of --file, --text and --stdin input
+        #[arg(short, long, conflicts_with_all = ["duration", "words"])]
+        code: bool,
+
+        /// In code mode, fill in indentation after each line break
+        #[arg(long, requires = "code")]
+        auto_indent: bool,
+
+        /// Add capitals and punctuation to random words
+        #[arg(short, long, conflicts_with_all = ["file", "text", "stdin", "code", "quote"])]
+        punctuation: bool,
+
+        /// Mix random numbers in with the words
+        #[arg(short, long, conflicts_with_all = ["file", "text", "stdin", "code", "quote"])]
+        numbers: bool,
+
+        /// Type one attributed quote, optionally of a given length
+        #[arg(short, long, value_enum, num_args = 0..=1, conflicts_with_all = ["duration", "words", "file", "text", "stdin", "code"])]
+        quote: Option<Option<QuoteLength>>,
+
+        /// Drill the given comma-separated n-grams, e.g. "::,->,=>", or
+        /// the language's most common bigrams and trigrams if none are given
+        #[arg(long, value_name = "LIST", num_args = 0..=1, conflicts_with_all = ["file", "text", "stdin", "code", "quote", "punctuation", "numbers", "mode"])]
+        ngrams: Option<Option<String>>,
+
+        /// How random words are chosen: uniformly, or weighted towards the
+        /// keys and bigrams you are slowest at or miss most
+        /// [default: random, or as set in the config file]
+        #[arg(long, value_enum, conflicts_with_all = ["file", "text", "stdin", "code", "quote"])]
+        mode: Option<PracticeMode>,
+
+        /// How mistakes are handled: every key moves on a character, or
+        /// space moves on a word
+        #[arg(long, value_enum, default_value_t = ErrorModeArg::Stream)]
+        error_mode: ErrorModeArg,
+
+        /// Refuse wrong keys until the right one is pressed, or refuse to
+        /// move past a word until its mistakes are fixed
+        #[arg(long, value_enum)]
+        strict: Option<StrictnessArg>,
+
+        /// End the test as failed on the first mistake
+        #[arg(long)]
+        sudden_death: bool,
+
+        /// Fail the test as soon as accuracy drops below this percentage,
+        /// after the first ten characters
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(1..=100))]
+        min_acc: Option<u32>,
+
+        /// Fail the test if speed drops below this many words per minute,
+        /// after the first five seconds
+        #[arg(long, value_name = "WPM", value_parser = clap::value_parser!(u32).range(1..))]
+        min_wpm: Option<u32>,
+
+        /// Colour scheme, bundled or from the config directory's themes folder
+        /// [default: default, or as set in the config file]
+        #[arg(long)]
+        theme: Option<String>,
+
+        /// Click on every key typed [default: off, or as set in the config file]
+        #[arg(long, overrides_with = "no_sound")]
+        sound: bool,
+
+        /// Don't click on keys, even if the config file turns sound on
+        #[arg(long)]
+        no_sound: bool,
+
         #[arg(long)]
         list_langs: bool,
     },
     
     Languages,
+
+    #[command(about = "List bundled and user themes")]
+    Themes,
+
+    #[command(
+        about = "Show results of past tests",
+        after_help = "Examples:\n  keyzen history\n  keyzen history --lang rust --since 2024-01-01\n  keyzen history --summary"
+    )]
+    History {
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        since: Option<String>,
+
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        until: Option<String>,
+
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        #[arg(short, long)]
+        summary: bool,
+    },
+
+    #[command(
+        about = "Show personal bests per language and mode",
+        after_help = "Examples:\n  keyzen pb\n  keyzen pb --lang rust\n  keyzen pb --reset --lang rust --duration 30\n  keyzen pb --reset --lang rust --words 50\n  keyzen pb --reset --lang rust --mode code --limit 12\n  keyzen pb --reset --lang english --duration 30 --punctuation"
+    )]
+    Pb {
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        #[arg(short, long, requires = "reset", conflicts_with = "words")]
+        duration: Option<u32>,
+
+        #[arg(short, long, requires = "reset", conflicts_with = "mode")]
+        words: Option<u32>,
+
+        /// Mode of the personal best to reset, for modes without a flag of their own
+        #[arg(short, long, value_enum, requires_all = ["reset", "limit"], conflicts_with = "duration")]
+        mode: Option<PbMode>,
+
+        /// Seconds, words or lines of the personal best to reset, as shown by `keyzen pb`
+        #[arg(long, requires = "mode")]
+        limit: Option<u32>,
+
+        /// Reset the personal best for tests with punctuation
+        #[arg(long, requires = "reset")]
+        punctuation: bool,
+
+        /// Reset the personal best for tests with numbers
+        #[arg(long, requires = "reset")]
+        numbers: bool,
+
+        #[arg(long, requires = "lang")]
+        reset: bool,
+    },
+
+    #[command(
+        about = "Show statistics gathered from past tests",
+        after_help = "Examples:\n  keyzen stats keys\n  keyzen stats keys --lang rust -n 5\n  keyzen stats heatmap --by latency"
+    )]
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
     
     Version,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PracticeMode {
+    Random,
+    Adaptive,
+}
+
+/// `--error-mode` values, one for each [`ErrorMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ErrorModeArg {
+    /// Every key press takes up the next character
+    Stream,
+    /// Space moves on to the next word, leaving the rest of the current one missed
+    Word,
+}
+
+impl From<ErrorModeArg> for ErrorMode {
+    fn from(arg: ErrorModeArg) -> Self {
+        match arg {
+            ErrorModeArg::Stream => ErrorMode::Stream,
+            ErrorModeArg::Word => ErrorMode::Word,
+        }
+    }
+}
+
+/// `--strict` values, one for each [`Strictness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StrictnessArg {
+    /// A wrong key is refused and the caret waits for the right one
+    Letter,
+    /// The caret can't move past a word until its mistakes are fixed
+    Word,
+}
+
+impl From<StrictnessArg> for Strictness {
+    fn from(arg: StrictnessArg) -> Self {
+        match arg {
+            StrictnessArg::Letter => Strictness::Letter,
+            StrictnessArg::Word => Strictness::Word,
+        }
+    }
+}
+
+/// Every mode a personal best can be recorded for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PbMode {
+    Time,
+    Words,
+    Text,
+    Quote,
+    Code,
+}
+
+impl PbMode {
+    fn with_limit(self, limit: u32) -> TestMode {
+        match self {
+            PbMode::Time => TestMode::Time(limit),
+            PbMode::Words => TestMode::Words(limit),
+            PbMode::Text => TestMode::Text(limit),
+            PbMode::Quote => TestMode::Quote(limit),
+            PbMode::Code => TestMode::Code(limit),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum StatsCommand {
+    /// Show the slowest and most error-prone keys
+    Keys {
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        /// How many keys to list in each table
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+
+        /// Ignore keys typed fewer than this many times
+        #[arg(long, default_value_t = 5)]
+        min_hits: u64,
+    },
+
+    /// Draw a keyboard coloured by error rate or latency
+    Heatmap {
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        /// Colour keys by error rate or by average latency
+        #[arg(long, value_enum, default_value_t = HeatmapMetric::Errors)]
+        by: HeatmapMetric,
+
+        /// Grey out keys typed fewer than this many times
+        #[arg(long, default_value_t = 5)]
+        min_hits: u64,
+    },
+}
+
+/// Per-key totals summed over every language, or just the one given.
+fn load_key_stats(lang: &Option<String>) -> Option<KeyStats> {
+    let all = match load_stats() {
+        Ok(all) => all,
+        Err(e) => {
+            eprintln!("Could not read key statistics: {}", e);
+            return None;
+        }
+    };
+    let mut stats = KeyStats::new();
+    for (language, language_stats) in &all {
+        if lang.is_none() || lang.as_ref() == Some(language) {
+            merge_into(&mut stats, language_stats);
+        }
+    }
+    if stats.is_empty() {
+        println!("No key statistics recorded yet.");
+        return None;
+    }
+    Some(stats)
+}
+
+/// Parses an optional date flag, printing an error and returning `None` if
+/// it is malformed.
+fn parse_date_arg(name: &str, value: &Option<String>) -> Option<Option<u64>> {
+    match value {
+        None => Some(None),
+        Some(date) => match parse_date(date) {
+            Some(ts) => Some(Some(ts)),
+            None => {
+                eprintln!("Invalid --{} date '{}', expected YYYY-MM-DD.", name, date);
+                None
+            }
+        },
+    }
+}
+
+/// Runs tests back to back until the user quits, building a fresh session
+/// for every restart, or retrying the missed words when asked.
+fn run_tests(
+    label: &str,
+    settings: &TuiSettings,
+    rules: Rules,
+    mut new_session: impl FnMut() -> TypingSession,
+) {
+    if let Err(e) = test_loop(label, settings, rules, &mut new_session) {
+        eprintln!("Error: {}", e);
+    }
+}
+
+fn test_loop(
+    label: &str,
+    settings: &TuiSettings,
+    rules: Rules,
+    new_session: &mut impl FnMut() -> TypingSession,
+) -> io::Result<()> {
+    // Held for the whole run so the screen doesn't flash between tests; the
+    // terminal is restored when it drops, before any error is printed.
+    let _screen = Screen::enter()?;
+    let mut session = new_session();
+    loop {
+        session.set_rules(rules);
+        session = match run_typing_test(session, label, settings)? {
+            NextTest::Restart => new_session(),
+            NextTest::Retry(words) => retry_session(words),
+            NextTest::Quit => return Ok(()),
+        };
+    }
+}
+
+/// A test made of just the words missed last time, shuffled and repeated so
+/// there is more than a moment's practice, and reported word by word.
+fn retry_session(missed: Vec<String>) -> TypingSession {
+    let count = (missed.len() * 3).max(10);
+    let words = generate_unlimited_words(&missed, count);
+    let mut session = TypingSession::new(words, TestMode::Words(count as u32));
+    session.set_drill(Drill::Retry);
+    session
+}
+
+/// Splits a comma-separated `--ngrams` list, rejecting entries that contain
+/// whitespace since each n-gram is typed as a word of its own.
+fn parse_ngrams(list: &str) -> Result<Vec<String>, String> {
+    let mut ngrams = Vec::new();
+    for ngram in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if ngram.chars().any(char::is_whitespace) {
+            return Err(format!(
+                "Invalid n-gram '{}': n-grams cannot contain spaces.",
+                ngram
+            ));
+        }
+        ngrams.push(ngram.to_string());
+    }
+    Ok(ngrams)
+}
+
+/// Reads the whole of a piped standard input. Keyboard events are then
+/// read from the controlling terminal (`/dev/tty` on Unix) by crossterm,
+/// so make sure there is one before starting the test.
+fn read_piped_stdin() -> Result<String, String> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Err("--stdin expects text to be piped in, e.g. `cat file.txt | keyzen start --stdin`.".to_string());
+    }
+
+    let mut content = String::new();
+    stdin
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Could not read standard input: {}", e))?;
+
+    #[cfg(unix)]
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|e| format!("Could not open the terminal for keyboard input: {}", e))?;
+
+    Ok(content)
+}
+
 fn main() {
     let cli = Cli::parse();
     match &cli.command {
-        Commands::Start { duration, lang, list_langs } => {
+        Commands::Start {
+            duration,
+            words,
+            lang,
+            file,
+            text,
+            stdin,
+            code,
+            auto_indent,
+            punctuation,
+            numbers,
+            quote,
+            mode,
+            ngrams,
+            error_mode,
+            strict,
+            sudden_death,
+            min_acc,
+            min_wpm,
+            theme,
+            sound,
+            no_sound,
+            list_langs,
+        } => {
             if *list_langs {
                 println!("Available languages:");
                 for lang in get_available_languages() {
@@ -53,6 +470,122 @@ fn main() {
                 return;
             }
 
+            let config = match load_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            let config_mode = match config
+                .mode
+                .as_deref()
+                .map(|m| PracticeMode::from_str(m, true))
+            {
+                Some(Ok(mode)) => Some(mode),
+                Some(Err(_)) => {
+                    eprintln!("Invalid mode in config file, expected random or adaptive.");
+                    return;
+                }
+                None => None,
+            };
+            // Command line flags win over the config file, which wins over
+            // the built-in defaults.
+            let duration = &duration.or(config.duration).unwrap_or(30);
+            let lang = &lang
+                .clone()
+                .or(config.lang)
+                .unwrap_or_else(|| "english".to_string());
+            let mode = mode.or(config_mode).unwrap_or(PracticeMode::Random);
+            let theme_name = theme
+                .clone()
+                .or(config.theme)
+                .unwrap_or_else(|| "default".to_string());
+            let theme = match load_theme(&theme_name) {
+                Ok(theme) => theme,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            let rules = Rules {
+                error_mode: (*error_mode).into(),
+                strict: strict.map(Strictness::from),
+                sudden_death: *sudden_death,
+                min_accuracy: *min_acc,
+                min_wpm: *min_wpm,
+            };
+            let defaults = TuiSettings::default();
+            let settings = TuiSettings {
+                words_per_line: config.words_per_line.unwrap_or(defaults.words_per_line),
+                visible_lines: config.visible_lines,
+                keys: config.keys,
+                theme,
+                allow_paste: config.allow_paste,
+                sound: match (sound, no_sound) {
+                    (true, _) => true,
+                    (_, true) => false,
+                    _ => config.sound.unwrap_or(false),
+                },
+            };
+
+            let custom_text = match (file, text) {
+                (Some(path), _) => match fs::read_to_string(path) {
+                    Ok(content) => {
+                        let name = Path::new(path)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_else(|| path.clone());
+                        Some((content, name))
+                    }
+                    Err(e) => {
+                        eprintln!("Could not read {}: {}", path, e);
+                        return;
+                    }
+                },
+                (None, Some(text)) => Some((text.clone(), "custom".to_string())),
+                (None, None) if *stdin => match read_piped_stdin() {
+                    Ok(content) => Some((content, "stdin".to_string())),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                },
+                (None, None) => None,
+            };
+
+            let code_session = |snippet: &str| {
+                let snippet = normalize_code(snippet);
+                let mut session = TypingSession::from_text(
+                    &snippet,
+                    TestMode::Code(snippet.lines().count() as u32),
+                );
+                session.set_auto_indent(*auto_indent);
+                session
+            };
+
+            if let Some((content, label)) = custom_text {
+                if *code {
+                    if normalize_code(&content).is_empty() {
+                        eprintln!("No text to type.");
+                        return;
+                    }
+                    run_tests(&label, &settings, rules, || code_session(&content));
+                    return;
+                }
+
+                let text_words = text_to_words(&content);
+                if text_words.is_empty() {
+                    eprintln!("No text to type.");
+                    return;
+                }
+                let mode = TestMode::Text(text_words.len() as u32);
+                run_tests(&label, &settings, rules, || {
+                    TypingSession::new(text_words.clone(), mode)
+                });
+                return;
+            }
+
             let available_langs = get_available_languages();
             if !available_langs.contains(lang) {
                 eprintln!("Language '{}' not found.", lang);
@@ -60,20 +593,101 @@ fn main() {
                 return;
             }
 
-            loop {
-                let word_pool = get_words(lang);
-                let estimated_words_needed = (*duration as f64 * 60.0 / 60.0) as usize; // 60 WPM estimate
-                let random_words = get_random_words(&word_pool, estimated_words_needed.max(50));
-                
-                match run_typing_test(random_words, *duration, lang) {
-                    Ok(()) => {
-                    },
+            if let Some(length) = quote {
+                let quotes = get_quotes(lang);
+                if get_random_quote(&quotes, *length).is_none() {
+                    eprintln!("No matching quotes available for '{}'.", lang);
+                    return;
+                }
+                run_tests(lang, &settings, rules, || {
+                    let quote = get_random_quote(&quotes, *length).expect("checked above");
+                    let quote_words = text_to_words(&quote.text);
+                    let mode = TestMode::Quote(quote_words.len() as u32);
+                    let mut session = TypingSession::new(quote_words, mode);
+                    session.set_source(quote.source);
+                    session
+                });
+                return;
+            }
+
+            if *code {
+                let snippets = get_snippets(lang);
+                if snippets.is_empty() {
+                    eprintln!("No code snippets available for '{}'.", lang);
+                    return;
+                }
+                run_tests(lang, &settings, rules, || {
+                    let snippet = get_random_snippet(&snippets).unwrap_or_default();
+                    code_session(&snippet)
+                });
+                return;
+            }
+
+            if let Some(list) = ngrams {
+                let drill = match list {
+                    Some(list) => parse_ngrams(list),
+                    None => Ok(common_ngrams(&get_words(lang), 6)),
+                };
+                let drill = match drill {
+                    Ok(drill) if !drill.is_empty() => drill,
+                    Ok(_) => {
+                        eprintln!("No n-grams to drill.");
+                        return;
+                    }
                     Err(e) => {
-                        eprintln!("Error: {}", e);
-                        break;
+                        eprintln!("{}", e);
+                        return;
                     }
-                }
+                };
+                run_tests(lang, &settings, rules, || {
+                    let mut session = match words {
+                        Some(count) => TypingSession::new(
+                            generate_unlimited_words(&drill, *count as usize),
+                            TestMode::Words(*count),
+                        ),
+                        None => {
+                            TypingSession::timed(drill.clone(), *duration, WordModifiers::default())
+                        }
+                    };
+                    session.set_drill(Drill::Ngrams);
+                    session
+                });
+                return;
             }
+
+            let modifiers = WordModifiers {
+                punctuation: *punctuation,
+                numbers: *numbers,
+            };
+            run_tests(lang, &settings, rules, || {
+                let word_pool = get_words(lang);
+                // Reloaded for every test so each one adapts to the last. If
+                // the stats can't be read, fall back to uniform choice; the
+                // results screen reports the problem when it saves them.
+                let weakness = match mode {
+                    PracticeMode::Random => None,
+                    PracticeMode::Adaptive => Some(Weakness::load(lang).unwrap_or_default()),
+                };
+                let pick_words = |pool: &[String], count: usize| match &weakness {
+                    Some(weakness) => get_weighted_words(pool, count, |w| weakness.word_weight(w)),
+                    None => get_random_words(pool, count),
+                };
+                match words {
+                    Some(count) => {
+                        let random_words = pick_words(&word_pool, (*count as usize).max(50));
+                        let target = generate_unlimited_words(&random_words, *count as usize);
+                        let mut session =
+                            TypingSession::new(modifiers.apply(target, true), TestMode::Words(*count));
+                        session.set_word_modifiers(modifiers);
+                        session
+                    }
+                    None => {
+                        let estimated_words_needed = (*duration as f64 * 60.0 / 60.0) as usize; // 60 WPM estimate
+                        let random_words = pick_words(&word_pool, estimated_words_needed.max(50));
+                        TypingSession::timed(random_words, *duration, modifiers)
+                    }
+                }
+            });
         }
         Commands::Languages => {
             println!("Available languages:");
@@ -82,6 +696,125 @@ fn main() {
                 println!("  {} ({} words)", lang, word_count);
             }
         }
+        Commands::History { lang, since, until, limit, summary } => {
+            let (Some(since), Some(until)) = (parse_date_arg("since", since), parse_date_arg("until", until)) else {
+                return;
+            };
+            let filter = HistoryFilter {
+                lang: lang.clone(),
+                since,
+                // `--until` includes the whole of the given day.
+                until: until.map(|ts| ts + 86_400),
+            };
+
+            let entries = match load_entries() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("Could not read history: {}", e);
+                    return;
+                }
+            };
+            if entries.is_empty() {
+                println!("No results recorded yet.");
+                return;
+            }
+            let matching: Vec<_> = entries.iter().filter(|e| filter.matches(e)).collect();
+            if matching.is_empty() {
+                println!("No results match the given filters.");
+                return;
+            }
+
+            if *summary {
+                print_summary(&matching);
+            } else {
+                let skip = matching.len().saturating_sub(*limit);
+                print_entries(&matching[skip..]);
+            }
+        }
+        Commands::Pb {
+            lang,
+            duration,
+            words,
+            mode,
+            limit,
+            punctuation,
+            numbers,
+            reset,
+        } => {
+            if *reset {
+                let mode = match (duration, words, mode, limit) {
+                    (Some(secs), ..) => TestMode::Time(*secs),
+                    (_, Some(count), ..) => TestMode::Words(*count),
+                    (_, _, Some(mode), Some(limit)) => mode.with_limit(*limit),
+                    _ => {
+                        eprintln!("Pass --duration, --words, or --mode with --limit to choose which personal best to reset.");
+                        return;
+                    }
+                };
+                let modifiers = WordModifiers {
+                    punctuation: *punctuation,
+                    numbers: *numbers,
+                };
+                let lang = lang.as_deref().unwrap_or_default();
+                let label = mode_label(mode.name(), mode.limit(), modifiers);
+                match reset_best(lang, mode.name(), mode.limit(), modifiers) {
+                    Ok(true) => println!("Reset personal best for {} ({}).", lang, label),
+                    Ok(false) => println!("No personal best recorded for {} ({}).", lang, label),
+                    Err(e) => eprintln!("Could not reset personal best: {}", e),
+                }
+                return;
+            }
+
+            let bests = match load_bests() {
+                Ok(bests) => bests,
+                Err(e) => {
+                    eprintln!("Could not read personal bests: {}", e);
+                    return;
+                }
+            };
+            let mut matching: Vec<_> = bests
+                .into_iter()
+                .filter(|pb| lang.is_none() || lang.as_ref() == Some(&pb.language))
+                .collect();
+            if matching.is_empty() {
+                println!("No personal bests recorded yet.");
+                return;
+            }
+            matching.sort_by(|a, b| {
+                (&a.language, &a.mode, a.limit, a.modifiers.punctuation, a.modifiers.numbers)
+                    .cmp(&(&b.language, &b.mode, b.limit, b.modifiers.punctuation, b.modifiers.numbers))
+            });
+            print_bests(&matching);
+        }
+        Commands::Stats {
+            command:
+                StatsCommand::Keys {
+                    lang,
+                    limit,
+                    min_hits,
+                },
+        } => {
+            let Some(stats) = load_key_stats(lang) else {
+                return;
+            };
+            print_keys(&stats, *limit, *min_hits);
+        }
+        Commands::Stats {
+            command: StatsCommand::Heatmap { lang, by, min_hits },
+        } => {
+            let Some(stats) = load_key_stats(lang) else {
+                return;
+            };
+            if let Err(e) = print_heatmap(&stats, *by, *min_hits) {
+                eprintln!("Could not draw heatmap: {}", e);
+            }
+        }
+        Commands::Themes => {
+            println!("Available themes:");
+            for name in available_themes() {
+                println!("  {}", name);
+            }
+        }
         Commands::Version => {
             println!("keyzen version {}", env!("CARGO_PKG_VERSION"));
         }
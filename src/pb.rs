@@ -0,0 +1,147 @@
+use serde_json::{json, Value};
+use std::io;
+
+use crate::game::WordModifiers;
+use crate::history::{format_timestamp, mode_label, modifiers_from_json, HistoryEntry};
+use crate::storage::{read_json, write_json};
+
+const PB_FILE: &str = "personal_bests.json";
+
+/// Best result for one language in one mode, e.g. rust / time 30. Tests
+/// with punctuation or numbers keep bests of their own.
+#[derive(Debug, Clone)]
+pub struct PersonalBest {
+    pub language: String,
+    pub mode: String,
+    pub limit: u32,
+    pub modifiers: WordModifiers,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub timestamp: u64,
+}
+
+impl PersonalBest {
+    fn from_entry(entry: &HistoryEntry) -> Self {
+        Self {
+            language: entry.language.clone(),
+            mode: entry.mode.clone(),
+            limit: entry.limit,
+            modifiers: entry.modifiers,
+            wpm: entry.wpm,
+            accuracy: entry.accuracy,
+            timestamp: entry.timestamp,
+        }
+    }
+
+    fn is_for(&self, language: &str, mode: &str, limit: u32, modifiers: WordModifiers) -> bool {
+        self.language == language
+            && self.mode == mode
+            && self.limit == limit
+            && self.modifiers == modifiers
+    }
+
+    pub fn mode_label(&self) -> String {
+        mode_label(&self.mode, self.limit, self.modifiers)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "language": self.language,
+            "mode": self.mode,
+            "limit": self.limit,
+            "punctuation": self.modifiers.punctuation,
+            "numbers": self.modifiers.numbers,
+            "wpm": self.wpm,
+            "accuracy": self.accuracy,
+            "timestamp": self.timestamp,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            language: value["language"].as_str()?.to_string(),
+            mode: value["mode"].as_str()?.to_string(),
+            limit: value["limit"].as_u64()? as u32,
+            modifiers: modifiers_from_json(value),
+            wpm: value["wpm"].as_f64()?,
+            accuracy: value["accuracy"].as_f64().unwrap_or(0.0),
+            timestamp: value["timestamp"].as_u64().unwrap_or(0),
+        })
+    }
+}
+
+/// How a finished test compares with the stored personal best.
+#[derive(Debug, Clone)]
+pub struct PbOutcome {
+    pub previous: Option<PersonalBest>,
+    pub is_new_best: bool,
+}
+
+pub fn load_bests() -> io::Result<Vec<PersonalBest>> {
+    let bests = match read_json(PB_FILE)? {
+        Some(Value::Array(items)) => items.iter().filter_map(PersonalBest::from_json).collect(),
+        _ => Vec::new(),
+    };
+    Ok(bests)
+}
+
+fn save_bests(bests: &[PersonalBest]) -> io::Result<()> {
+    let items: Vec<Value> = bests.iter().map(PersonalBest::to_json).collect();
+    write_json(PB_FILE, &Value::Array(items))
+}
+
+/// Compares a finished test against the stored best for its language and
+/// mode, replacing the stored best if it was beaten.
+pub fn record_result(entry: &HistoryEntry) -> io::Result<PbOutcome> {
+    let mut bests = load_bests()?;
+    let index = bests
+        .iter()
+        .position(|pb| pb.is_for(&entry.language, &entry.mode, entry.limit, entry.modifiers));
+    let previous = index.map(|i| bests[i].clone());
+    let is_new_best = previous.as_ref().map_or(entry.wpm > 0.0, |pb| entry.wpm > pb.wpm);
+
+    if is_new_best {
+        let best = PersonalBest::from_entry(entry);
+        match index {
+            Some(i) => bests[i] = best,
+            None => bests.push(best),
+        }
+        save_bests(&bests)?;
+    }
+
+    Ok(PbOutcome { previous, is_new_best })
+}
+
+/// Removes a single stored best. Returns false if there was nothing to remove.
+pub fn reset_best(
+    language: &str,
+    mode: &str,
+    limit: u32,
+    modifiers: WordModifiers,
+) -> io::Result<bool> {
+    let mut bests = load_bests()?;
+    let before = bests.len();
+    bests.retain(|pb| !pb.is_for(language, mode, limit, modifiers));
+    if bests.len() == before {
+        return Ok(false);
+    }
+    save_bests(&bests)?;
+    Ok(true)
+}
+
+pub fn print_bests(bests: &[PersonalBest]) {
+    println!(
+        "{:<12}  {:<20}  {:>5}  {:>5}  date",
+        "language", "mode", "wpm", "acc"
+    );
+    for pb in bests {
+        println!(
+            "{:<12}  {:<20}  {:>5.0}  {:>4.0}%  {}",
+            pb.language,
+            pb.mode_label(),
+            pb.wpm,
+            pb.accuracy,
+            format_timestamp(pb.timestamp)
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use rodio::source::{SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle};
+use std::time::Duration;
+
+const CLICK_LENGTH: Duration = Duration::from_millis(12);
+const CORRECT_PITCH: f32 = 1200.0;
+const MISTAKE_PITCH: f32 = 300.0;
+
+/// Short clicks played as keys are typed, lower for a mistake.
+pub struct Clicker {
+    // Sound stops when the stream is dropped, so it is kept alongside the
+    // handle that plays into it.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl Clicker {
+    /// Opens the default audio output, or `None` if there isn't one.
+    pub fn open() -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+        Some(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+
+    pub fn click(&self, correct: bool) {
+        let pitch = if correct {
+            CORRECT_PITCH
+        } else {
+            MISTAKE_PITCH
+        };
+        let tone = SineWave::new(pitch)
+            .take_duration(CLICK_LENGTH)
+            .amplify(0.15);
+        // A missed click isn't worth interrupting the test for.
+        let _ = self.handle.play_raw(tone);
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+    Some(base.join("keyzen"))
+}
+
+pub fn data_file(name: &str) -> io::Result<PathBuf> {
+    data_dir()
+        .map(|dir| dir.join(name))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not locate data directory"))
+}
+
+/// Reads a whole JSON document. A missing file yields `None`.
+pub fn read_json(name: &str) -> io::Result<Option<Value>> {
+    let path = data_file(name)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replaces a JSON document, writing to a temporary file first so a crash
+/// never leaves it half written.
+pub fn write_json(name: &str, value: &Value) -> io::Result<()> {
+    let path = data_file(name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(tmp, path)
+}
+
+/// Reads a JSON Lines file, skipping lines that fail to parse. A missing
+/// file is treated as empty.
+pub fn read_json_lines(name: &str) -> io::Result<Vec<Value>> {
+    let path = data_file(name)?;
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut values = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(&line) {
+            Ok(value) => values.push(value),
+            Err(_) => eprintln!("Skipping invalid line in {}", path.display()),
+        }
+    }
+    Ok(values)
+}
+
+pub fn append_json_line(name: &str, value: &Value) -> io::Result<()> {
+    let path = data_file(name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", value)
+}
@@ -0,0 +1,259 @@
+use crossterm::style::Color;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::config_dir;
+
+/// Colours for everything drawn on the test and results screens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub correct: Color,
+    pub incorrect: Color,
+    pub pending: Color,
+    pub caret_fg: Color,
+    pub caret_bg: Color,
+    /// The countdown or word count above the text.
+    pub timer: Color,
+    /// Live WPM and the line on the speed graph.
+    pub accent: Color,
+    /// Separators, instructions and other secondary text.
+    pub subtle: Color,
+    /// Labels on the results screen.
+    pub text: Color,
+    /// Scores from best to worst, used for WPM and accuracy.
+    pub great: Color,
+    pub good: Color,
+    pub fair: Color,
+    pub poor: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            correct: Color::Green,
+            incorrect: Color::Red,
+            pending: Color::DarkGrey,
+            caret_fg: Color::Black,
+            caret_bg: Color::White,
+            timer: Color::Yellow,
+            accent: Color::Cyan,
+            subtle: Color::DarkGrey,
+            text: Color::Grey,
+            great: Color::Green,
+            good: Color::Cyan,
+            fair: Color::Yellow,
+            poor: Color::Red,
+        }
+    }
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color::Rgb {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+    }
+}
+
+/// Themes shipped with keyzen, by name.
+fn bundled(name: &str) -> Option<Theme> {
+    let theme = match name {
+        "default" => Theme::default(),
+        "light" => Theme {
+            correct: Color::DarkGreen,
+            incorrect: Color::DarkRed,
+            pending: rgb(0x8a8a8a),
+            caret_fg: Color::White,
+            caret_bg: Color::Black,
+            timer: Color::DarkYellow,
+            accent: Color::DarkBlue,
+            subtle: rgb(0x8a8a8a),
+            text: rgb(0x3a3a3a),
+            great: Color::DarkGreen,
+            good: Color::DarkBlue,
+            fair: Color::DarkYellow,
+            poor: Color::DarkRed,
+        },
+        "solarized-dark" | "solarized-light" => {
+            let (pending, text, caret) = if name == "solarized-dark" {
+                (rgb(0x586e75), rgb(0x93a1a1), rgb(0xeee8d5))
+            } else {
+                (rgb(0x93a1a1), rgb(0x586e75), rgb(0x073642))
+            };
+            Theme {
+                correct: rgb(0x859900),
+                incorrect: rgb(0xdc322f),
+                pending,
+                caret_fg: rgb(0x002b36),
+                caret_bg: caret,
+                timer: rgb(0xb58900),
+                accent: rgb(0x268bd2),
+                subtle: pending,
+                text,
+                great: rgb(0x859900),
+                good: rgb(0x2aa198),
+                fair: rgb(0xb58900),
+                poor: rgb(0xdc322f),
+            }
+        }
+        "dracula" => Theme {
+            correct: rgb(0x50fa7b),
+            incorrect: rgb(0xff5555),
+            pending: rgb(0x6272a4),
+            caret_fg: rgb(0x282a36),
+            caret_bg: rgb(0xf8f8f2),
+            timer: rgb(0xf1fa8c),
+            accent: rgb(0xbd93f9),
+            subtle: rgb(0x6272a4),
+            text: rgb(0xf8f8f2),
+            great: rgb(0x50fa7b),
+            good: rgb(0x8be9fd),
+            fair: rgb(0xf1fa8c),
+            poor: rgb(0xff5555),
+        },
+        "nord" => Theme {
+            correct: rgb(0xa3be8c),
+            incorrect: rgb(0xbf616a),
+            pending: rgb(0x616e88),
+            caret_fg: rgb(0x2e3440),
+            caret_bg: rgb(0xd8dee9),
+            timer: rgb(0xebcb8b),
+            accent: rgb(0x88c0d0),
+            subtle: rgb(0x616e88),
+            text: rgb(0xd8dee9),
+            great: rgb(0xa3be8c),
+            good: rgb(0x88c0d0),
+            fair: rgb(0xebcb8b),
+            poor: rgb(0xbf616a),
+        },
+        _ => return None,
+    };
+    Some(theme)
+}
+
+const BUNDLED_THEMES: [&str; 6] = [
+    "default",
+    "light",
+    "solarized-dark",
+    "solarized-light",
+    "dracula",
+    "nord",
+];
+
+fn themes_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("themes"))
+}
+
+/// Names of the bundled themes followed by any user themes.
+pub fn available_themes() -> Vec<String> {
+    let mut names: Vec<String> = BUNDLED_THEMES.iter().map(|n| n.to_string()).collect();
+    if let Some(entries) = themes_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        let mut user: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .filter(|name| !names.contains(name))
+            .collect();
+        user.sort();
+        names.append(&mut user);
+    }
+    names
+}
+
+/// Loads a theme by name. A user theme in `themes/<name>.json` under the
+/// config directory takes precedence over a bundled theme of the same name.
+pub fn load_theme(name: &str) -> Result<Theme, String> {
+    if let Some(path) = themes_dir().map(|dir| dir.join(format!("{}.json", name))) {
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let value: Value = serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid theme {}: {}", path.display(), e))?;
+                return theme_from_json(&value)
+                    .map_err(|e| format!("Invalid theme {}: {}", path.display(), e));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        }
+    }
+    bundled(name).ok_or_else(|| {
+        format!(
+            "Theme '{}' not found.\nAvailable: {}",
+            name,
+            available_themes().join(", ")
+        )
+    })
+}
+
+/// A user theme is a JSON object of colours, starting from the theme named
+/// by `"base"` (or the default theme) and replacing whichever colours it
+/// lists.
+fn theme_from_json(value: &Value) -> Result<Theme, String> {
+    let Some(fields) = value.as_object() else {
+        return Err("expected a JSON object".to_string());
+    };
+    let mut theme = match fields.get("base").and_then(Value::as_str) {
+        Some(base) => bundled(base).ok_or_else(|| format!("unknown base theme '{}'", base))?,
+        None => Theme::default(),
+    };
+
+    for (key, value) in fields {
+        if key == "base" {
+            continue;
+        }
+        let slot = match key.as_str() {
+            "correct" => &mut theme.correct,
+            "incorrect" => &mut theme.incorrect,
+            "pending" => &mut theme.pending,
+            "caret_fg" => &mut theme.caret_fg,
+            "caret_bg" => &mut theme.caret_bg,
+            "timer" => &mut theme.timer,
+            "accent" => &mut theme.accent,
+            "subtle" => &mut theme.subtle,
+            "text" => &mut theme.text,
+            "great" => &mut theme.great,
+            "good" => &mut theme.good,
+            "fair" => &mut theme.fair,
+            "poor" => &mut theme.poor,
+            _ => return Err(format!("unknown colour '{}'", key)),
+        };
+        let color = value.as_str().and_then(parse_color).ok_or_else(|| {
+            format!(
+                "'{}' must be a colour like \"#a3be8c\" or \"dark_grey\"",
+                key
+            )
+        })?;
+        *slot = color;
+    }
+    Ok(theme)
+}
+
+/// Parses `#rrggbb`, `#rgb`, an ANSI colour number or a colour name such as
+/// `dark_grey`.
+fn parse_color(text: &str) -> Option<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return match hex.len() {
+            6 => Some(rgb(value)),
+            3 => {
+                let expand = |nibble: u32| ((nibble & 0xf) * 0x11) as u8;
+                Some(Color::Rgb {
+                    r: expand(value >> 8),
+                    g: expand(value >> 4),
+                    b: expand(value),
+                })
+            }
+            _ => None,
+        };
+    }
+    if let Ok(ansi) = text.parse::<u8>() {
+        return Some(Color::AnsiValue(ansi));
+    }
+    Color::try_from(text).ok()
+}
@@ -1,51 +1,207 @@
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode, KeyEventKind},
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
-    terminal::{self, ClearType},
-    ExecutableCommand,
+    terminal, ExecutableCommand,
 };
-use std::io::stdout;
+use std::io::{self, stdout, Stdout};
 use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::frame::Frame;
+use crate::game::{CharStatus, KeyAction, SpeedSample, TestMode, TypingSession};
+use crate::history::{self, HistoryEntry};
+use crate::input::{pasted_chars, Composer};
+use crate::keystats;
+use crate::pb::{self, PbOutcome};
+use crate::sound::Clicker;
+use crate::theme::Theme;
+
+/// Keys for the actions that aren't typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBindings {
+    /// Ends the test early and shows the results.
+    pub finish: KeyCode,
+    pub restart: KeyCode,
+    pub retry: KeyCode,
+    pub quit: KeyCode,
+}
 
-fn generate_unlimited_words(original_words: &[String], estimated_needed: usize) -> Vec<String> {
-    use rand::seq::SliceRandom;
-    let mut rng = rand::thread_rng();
-    let mut result = Vec::new();
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            finish: KeyCode::Esc,
+            restart: KeyCode::Tab,
+            retry: KeyCode::Char('r'),
+            quit: KeyCode::Esc,
+        }
+    }
+}
 
-    while result.len() < estimated_needed {
-        let batch_size = original_words.len().min(estimated_needed - result.len());
-        let mut batch: Vec<String> = original_words
-            .choose_multiple(&mut rng, batch_size)
-            .cloned()
-            .collect();
-        result.append(&mut batch);
+/// Layout, key and colour choices for the test and results screens.
+#[derive(Debug, Clone, Copy)]
+pub struct TuiSettings {
+    /// Words per line for random words; texts and quotes wrap to the
+    /// terminal width and code keeps its own lines.
+    pub words_per_line: usize,
+    /// Lines of text shown at once, or `None` for 3 (8 for code).
+    pub visible_lines: Option<usize>,
+    pub keys: KeyBindings,
+    pub theme: Theme,
+    /// Type text pasted into the terminal rather than ignoring it.
+    pub allow_paste: bool,
+    /// Click on every key typed, with a lower click for mistakes.
+    pub sound: bool,
+}
+
+impl Default for TuiSettings {
+    fn default() -> Self {
+        Self {
+            words_per_line: 10,
+            visible_lines: None,
+            keys: KeyBindings::default(),
+            theme: Theme::default(),
+            allow_paste: false,
+            sound: false,
+        }
     }
+}
 
-    result
+/// Name of a key as shown in the on-screen instructions.
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        other => format!("{:?}", other).to_lowercase(),
+    }
 }
 
-fn create_text_lines(words: &[String], words_per_line: usize) -> Vec<String> {
+/// A run of the target shown on one row. `start` and `len` count graphemes,
+/// `width` the terminal columns they take up.
+struct TextLine {
+    start: usize,
+    len: usize,
+    width: usize,
+}
+
+/// Greedily fills lines up to `max_width` columns and at most `max_words`
+/// words, breaking between words. A word too wide for a line of its own is
+/// split between graphemes, the next line carrying on where it stopped.
+fn wrap_text_lines(words: &[String], max_width: usize, max_words: usize) -> Vec<TextLine> {
     let mut lines = Vec::new();
-    let mut current_line = Vec::new();
+    let mut start = 0;
+    let mut len = 0;
+    let mut width = 0;
+    let mut count = 0;
 
     for word in words {
-        current_line.push(word.clone());
-        if current_line.len() >= words_per_line {
-            lines.push(current_line.join(" "));
-            current_line.clear();
+        let word_len = word.graphemes(true).count();
+        let word_width = word.width();
+        if len > 0 && (width + 1 + word_width > max_width || count == max_words) {
+            lines.push(TextLine { start, len, width });
+            start += len + 1;
+            len = 0;
+            width = 0;
+            count = 0;
+        }
+        if len > 0 {
+            len += 1;
+            width += 1;
+        }
+        count += 1;
+        if word_width <= max_width {
+            len += word_len;
+            width += word_width;
+            continue;
+        }
+        for grapheme in word.graphemes(true) {
+            let grapheme_width = grapheme.width();
+            if len > 0 && width + grapheme_width > max_width {
+                lines.push(TextLine { start, len, width });
+                start += len;
+                len = 0;
+                width = 0;
+            }
+            len += 1;
+            width += grapheme_width;
         }
     }
 
-    if !current_line.is_empty() {
-        lines.push(current_line.join(" "));
+    if len > 0 {
+        lines.push(TextLine { start, len, width });
     }
 
     lines
 }
 
+/// One line per line of the target, for code where the line breaks are part
+/// of what is typed. Each line's trailing newline sits at `start + len`.
+fn split_code_lines(target: &[String]) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (i, grapheme) in target.iter().enumerate() {
+        if grapheme == "\n" {
+            lines.push(TextLine {
+                start,
+                len: i - start,
+                width: target[start..i].concat().width(),
+            });
+            start = i + 1;
+        }
+    }
+    lines.push(TextLine {
+        start,
+        len: target.len() - start,
+        width: target[start..].concat().width(),
+    });
+
+    lines
+}
+
+/// Narrowest terminal the test and results screens are drawn in.
+const MIN_WIDTH: u16 = 30;
+
+/// Shown in place of a screen that doesn't fit, until the terminal is
+/// resized.
+fn draw_too_small(frame: &mut Frame, theme: &Theme, min_width: u16, min_height: u16) {
+    let (width, height) = frame.size();
+    let lines = [
+        "terminal too small".to_string(),
+        format!(
+            "need {}x{}, have {}x{}",
+            min_width, min_height, width, height
+        ),
+    ];
+    let top = (height / 2).saturating_sub(1);
+    for (i, line) in lines.iter().enumerate() {
+        frame.move_to(center_text(line, width), top + i as u16);
+        frame.set_fg(if i == 0 {
+            theme.incorrect
+        } else {
+            theme.subtle
+        });
+        frame.print(line);
+    }
+    frame.reset_color();
+}
+
+/// Stand-in for characters that would move the cursor if printed.
+fn visible_char(grapheme: &str) -> &str {
+    if grapheme == "\n" {
+        "↵"
+    } else {
+        grapheme
+    }
+}
+
 fn center_text(text: &str, width: u16) -> u16 {
-    let text_len = text.chars().count() as u16;
+    let text_len = text.width().min(u16::MAX as usize) as u16;
     if text_len >= width {
         return 0;
     }
@@ -63,356 +219,913 @@ fn format_time(seconds: u64) -> String {
 }
 
 fn draw_header_stats(
-    stdout: &mut std::io::Stdout,
+    frame: &mut Frame,
+    theme: &Theme,
     terminal_width: u16,
     y_pos: u16,
-    secs_left: u64,
-    current_wpm: u32,
-    current_accuracy: u32,
-    start_time: Option<Instant>,
-) -> std::io::Result<()> {
-    if start_time.is_none() {
-        let timer_text = format_time(secs_left);
-        let timer_x = center_text(&timer_text, terminal_width);
-        stdout.execute(cursor::MoveTo(timer_x, y_pos))?;
-        stdout.execute(SetForegroundColor(Color::Yellow))?;
-        stdout.execute(Print(&timer_text))?;
-        stdout.execute(ResetColor)?;
-        return Ok(());
-    }
-
-    let timer_text = format_time(secs_left);
+    timer_text: &str,
+    live_stats: Option<(u32, u32)>,
+) {
+    let Some((current_wpm, current_accuracy)) = live_stats else {
+        let timer_x = center_text(timer_text, terminal_width);
+        frame.move_to(timer_x, y_pos);
+        frame.set_fg(theme.timer);
+        frame.print(timer_text);
+        frame.reset_color();
+        return;
+    };
+
     let stats_line = format!(
         "{} │ {} wpm │ {}% acc",
         timer_text, current_wpm, current_accuracy
     );
 
     let stats_x = center_text(&stats_line, terminal_width);
-    stdout.execute(cursor::MoveTo(stats_x, y_pos))?;
+    frame.move_to(stats_x, y_pos);
 
-    stdout.execute(SetForegroundColor(Color::Yellow))?;
-    stdout.execute(Print(&timer_text))?;
+    frame.set_fg(theme.timer);
+    frame.print(timer_text);
 
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(" │ "))?;
+    frame.set_fg(theme.subtle);
+    frame.print(" │ ");
 
-    stdout.execute(SetForegroundColor(Color::Cyan))?;
-    stdout.execute(Print(&format!("{} wpm", current_wpm)))?;
+    frame.set_fg(theme.accent);
+    frame.print(format!("{} wpm", current_wpm));
 
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(" │ "))?;
+    frame.set_fg(theme.subtle);
+    frame.print(" │ ");
 
     let acc_color = match current_accuracy {
-        95..=100 => Color::Green,
-        80..=94 => Color::Yellow,
-        _ => Color::Red,
+        95..=100 => theme.great,
+        80..=94 => theme.fair,
+        _ => theme.poor,
     };
-    stdout.execute(SetForegroundColor(acc_color))?;
-    stdout.execute(Print(&format!("{}% acc", current_accuracy)))?;
+    frame.set_fg(acc_color);
+    frame.print(format!("{}% acc", current_accuracy));
 
-    stdout.execute(ResetColor)?;
-    Ok(())
+    frame.reset_color();
 }
 
-pub fn run_typing_test(words: Vec<String>, duration: u32, lang: &str) -> std::io::Result<()> {
-    let mut stdout = stdout();
-    terminal::enable_raw_mode()?;
-    stdout.execute(cursor::Hide)?;
+/// What the user chose to do from the results screen.
+pub enum NextTest {
+    Restart,
+    /// Retry just these words, the ones missed in the last test.
+    Retry(Vec<String>),
+    Quit,
+}
+
+/// Raw mode, a hidden cursor and the alternate screen, for as long as the
+/// value lives. Dropping it (even while unwinding from a panic) puts the
+/// terminal back, with the user's scrollback untouched.
+pub struct Screen;
+
+impl Screen {
+    pub fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // Made first so a failure below still restores raw mode on drop.
+        let screen = Screen;
+        let mut stdout = stdout();
+        stdout.execute(terminal::EnterAlternateScreen)?;
+        stdout.execute(cursor::Hide)?;
+        // Pasted text then arrives as one event rather than as keystrokes.
+        stdout.execute(event::EnableBracketedPaste)?;
+        Ok(screen)
+    }
+}
 
-    if words.is_empty() {
-        eprintln!("No words to type!");
-        return Ok(());
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let mut stdout = stdout();
+        let _ = stdout.execute(event::DisableBracketedPaste);
+        let _ = stdout.execute(cursor::Show);
+        let _ = stdout.execute(terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
     }
+}
 
-    let words_per_line = 10;
-    let visible_lines = 3;
+/// Runs one test, drawing `session` and feeding it keystrokes until it
+/// finishes or the user quits.
+pub fn run_typing_test(
+    mut session: TypingSession,
+    lang: &str,
+    settings: &TuiSettings,
+) -> io::Result<NextTest> {
+    let mut stdout = stdout();
 
-    let mut typed = String::new();
-    let test_duration = Duration::from_secs(duration as u64);
-    let mut start_time: Option<Instant> = None;
-    let mut correct_chars: usize = 0;
-    let mut total_typed_chars: usize = 0;
-    let mut current_line_set = 0;
+    if session.target().is_empty() && !session.has_word_pool() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no words to type",
+        ));
+    }
 
-    let word_pool = words.clone();
-    let mut all_text_lines = Vec::new();
+    let mode = session.mode();
+    let is_code = matches!(mode, TestMode::Code(_));
+    let words_per_line = settings.words_per_line;
+    let visible_lines = settings
+        .visible_lines
+        .unwrap_or(if is_code { 8 } else { 3 });
+    let keys = settings.keys;
+    let theme = &settings.theme;
+    let mut previous: Option<Frame> = None;
+    let mut composer = Composer::default();
+    let clicker = if settings.sound {
+        Clicker::open()
+    } else {
+        None
+    };
 
     loop {
         let (terminal_width, terminal_height) = terminal::size()?;
-        let elapsed = start_time.map(|t| t.elapsed()).unwrap_or(Duration::ZERO);
+        let now = Instant::now();
 
-        if start_time.is_some() && elapsed >= test_duration {
+        session.check_limits(now);
+        if session.is_finished(now) {
+            session.finish(now);
             break;
         }
 
+        let text_width = (terminal_width as usize).saturating_sub(10).clamp(20, 80);
+        let layout = |session: &TypingSession| match mode {
+            TestMode::Text(_) | TestMode::Quote(_) => {
+                wrap_text_lines(session.words(), text_width, usize::MAX)
+            }
+            TestMode::Code(_) => split_code_lines(session.target()),
+            _ => wrap_text_lines(session.words(), text_width, words_per_line),
+        };
+
+        let mut lines = layout(&session);
+        let caret_pos = session.caret();
+        // The gap after a line belongs to it, but the first grapheme of a
+        // line carrying on a split word doesn't.
+        let caret_line = lines
+            .iter()
+            .rposition(|line| line.start <= caret_pos)
+            .unwrap_or(0);
+        let current_line_set = caret_line / visible_lines;
+
         let needed_lines = (current_line_set + 1) * visible_lines + 10; // Buffer
-        while all_text_lines.len() < needed_lines {
-            let more_words = generate_unlimited_words(&word_pool, words_per_line * 20);
-            let more_lines = create_text_lines(&more_words, words_per_line);
-            all_text_lines.extend(more_lines);
+        if session.has_word_pool() && lines.len() < needed_lines {
+            session.extend_from_pool(words_per_line * 20);
+            lines = layout(&session);
         }
 
-        stdout.execute(cursor::MoveTo(0, 0))?;
-        stdout.execute(terminal::Clear(ClearType::All))?;
+        let mut frame = Frame::new(terminal_width, terminal_height);
+
+        // Code keeps its indentation, so the block is centred as a whole
+        // rather than line by line, and can't be wrapped to fit.
+        let code_width = lines.iter().map(|line| line.width).max().unwrap_or(0) + 1;
+        let min_width = if is_code {
+            (code_width as u16 + 2).max(MIN_WIDTH)
+        } else {
+            MIN_WIDTH
+        };
+        // Three rows of header above the text, instructions two rows below.
+        let min_height = visible_lines as u16 + 7;
+        if terminal_width < min_width || terminal_height < min_height {
+            draw_too_small(&mut frame, theme, min_width, min_height);
+            frame.render(&mut stdout, previous.as_ref())?;
+            previous = Some(frame);
+
+            // Typing blind would only cause mistakes, so just the finish key
+            // works until the terminal is big enough again.
+            if event::poll(Duration::from_millis(50))? {
+                if let Event::Key(key_event) = event::read()? {
+                    if key_event.kind == KeyEventKind::Press && key_event.code == keys.finish {
+                        session.finish(Instant::now());
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
 
         let center_y = terminal_height / 2;
 
-        let current_wpm = if start_time.is_some() && elapsed.as_secs() > 0 {
-            let minutes = elapsed.as_secs_f64() / 60.0;
-            (correct_chars as f64 / 5.0 / minutes).round() as u32
+        let current_wpm = if session.elapsed(now).as_secs() > 0 {
+            session.wpm(now).round() as u32
         } else {
             0
         };
 
-        let current_accuracy = if total_typed_chars > 0 {
-            ((correct_chars as f64 / total_typed_chars as f64) * 100.0).round() as u32
-        } else {
-            100
-        };
+        let current_accuracy = session.accuracy().unwrap_or(100.0).round() as u32;
 
-        let secs_left = if start_time.is_some() {
-            test_duration.saturating_sub(elapsed).as_secs()
-        } else {
-            duration as u64
+        let progress_text = match session.time_left(now) {
+            Some(left) => format_time(left.as_secs()),
+            None => format!("{}/{}", session.words_typed(), session.words().len()),
         };
 
-        let header_y = if center_y >= 4 { center_y - 4 } else { 0 };
+        let block_top = center_y.saturating_sub(visible_lines as u16 / 2);
+        let header_y = block_top.saturating_sub(3);
         draw_header_stats(
-            &mut stdout,
+            &mut frame,
+            theme,
             terminal_width,
             header_y,
-            secs_left,
-            current_wpm,
-            current_accuracy,
-            start_time,
-        )?;
+            &progress_text,
+            session
+                .is_started()
+                .then_some((current_wpm, current_accuracy)),
+        );
 
         let start_line = current_line_set * visible_lines;
-        let current_visible_lines: Vec<String> = all_text_lines
+        let target = session.target();
+        let typed = session.typed();
+
+        let code_x = center_text(&" ".repeat(code_width), terminal_width);
+
+        for (line_idx, line) in lines
             .iter()
             .skip(start_line)
             .take(visible_lines)
-            .cloned()
-            .collect();
-
-        let target_text = current_visible_lines.join(" ");
-        let caret_pos = typed.chars().count();
-
-        for (line_idx, line) in current_visible_lines.iter().enumerate() {
-            let line_y = if center_y >= 1 { center_y - 1 } else { 0 } + line_idx as u16;
-            let line_x = center_text(line, terminal_width);
-            stdout.execute(cursor::MoveTo(line_x, line_y))?;
-
-            let line_start_pos = if line_idx == 0 {
-                0
+            .enumerate()
+        {
+            let line_y = block_top + line_idx as u16;
+            let line_chars = &target[line.start..line.start + line.len];
+            let line_x = if is_code {
+                code_x
             } else {
-                current_visible_lines[..line_idx]
-                    .iter()
-                    .map(|l| l.len() + 1) // +1 for space between lines
-                    .sum::<usize>()
+                (terminal_width as usize).saturating_sub(line.width) as u16 / 2
             };
+            frame.move_to(line_x, line_y);
 
-            for (char_idx, target_ch) in line.chars().enumerate() {
-                let global_pos = line_start_pos + char_idx;
+            for (char_idx, target_ch) in line_chars.iter().enumerate() {
+                let global_pos = line.start + char_idx;
                 let is_caret = global_pos == caret_pos;
 
-                stdout.execute(ResetColor)?;
+                frame.set_fg(theme.incorrect);
+                for extra in session.overflow(global_pos) {
+                    frame.print(extra);
+                }
+                frame.reset_color();
 
-                if let Some(typed_ch) = typed.chars().nth(global_pos) {
-                    if typed_ch == target_ch {
-                        stdout.execute(SetForegroundColor(Color::Green))?;
-                        stdout.execute(Print(typed_ch))?;
-                    } else {
-                        stdout.execute(SetForegroundColor(Color::Red))?;
-                        stdout.execute(Print(typed_ch))?;
+                match session.char_status(global_pos) {
+                    CharStatus::Correct => {
+                        frame.set_fg(theme.correct);
+                        frame.print(&typed[global_pos]);
+                    }
+                    CharStatus::Incorrect => {
+                        frame.set_fg(theme.incorrect);
+                        // A mistake of a different width, such as a Latin
+                        // letter for a CJK one, would shift the rest of the
+                        // line, so the expected grapheme is shown instead.
+                        let shown = visible_char(&typed[global_pos]);
+                        if shown.width() == target_ch.width() {
+                            frame.print(shown);
+                        } else {
+                            frame.print(target_ch);
+                        }
                     }
-                } else {
-                    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-                    if is_caret {
-                        stdout.execute(SetBackgroundColor(Color::White))?;
-                        stdout.execute(SetForegroundColor(Color::Black))?;
+                    CharStatus::Pending => {
+                        frame.set_fg(theme.pending);
+                        if is_caret {
+                            frame.set_bg(theme.caret_bg);
+                            frame.set_fg(theme.caret_fg);
+                        }
+                        // A dead key waiting for its letter shows in place.
+                        match composer.pending() {
+                            Some(accent) if is_caret && target_ch.width() == 1 => {
+                                frame.print(accent)
+                            }
+                            _ => frame.print(target_ch),
+                        }
                     }
-                    stdout.execute(Print(target_ch))?;
                 }
 
-                stdout.execute(ResetColor)?;
+                frame.reset_color();
+            }
+
+            let newline_pos = line.start + line.len;
+            frame.set_fg(theme.incorrect);
+            for extra in session.overflow(newline_pos) {
+                frame.print(extra);
+            }
+            frame.reset_color();
+            if is_code && target.get(newline_pos).is_some_and(|g| g == "\n") {
+                match session.char_status(newline_pos) {
+                    CharStatus::Incorrect => {
+                        frame.set_fg(theme.incorrect);
+                        frame.print(visible_char(&typed[newline_pos]));
+                    }
+                    CharStatus::Pending if newline_pos == caret_pos => {
+                        frame.set_bg(theme.caret_bg);
+                        frame.set_fg(theme.caret_fg);
+                        frame.print('↵');
+                    }
+                    _ => {}
+                }
+                frame.reset_color();
             }
         }
 
-        let instructions = if start_time.is_none() {
-            "press any key to start typing"
+        let instructions = if !session.is_started() {
+            "press any key to start typing".to_string()
+        } else if is_code {
+            format!(
+                "{}: quit • enter: new line • backspace: delete",
+                key_name(keys.finish)
+            )
         } else {
-            "esc: quit • backspace: delete"
+            format!("{}: quit • backspace: delete", key_name(keys.finish))
         };
-        let inst_x = center_text(instructions, terminal_width);
-        let inst_y = center_y.saturating_add(4);
-        stdout.execute(cursor::MoveTo(inst_x, inst_y))?;
-        stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-        stdout.execute(Print(instructions))?;
-        stdout.execute(ResetColor)?;
+        let inst_x = center_text(&instructions, terminal_width);
+        let inst_y = block_top.saturating_add(visible_lines as u16 + 2);
+        frame.move_to(inst_x, inst_y);
+        frame.set_fg(theme.subtle);
+        frame.print(&instructions);
+        frame.reset_color();
+
+        frame.render(&mut stdout, previous.as_ref())?;
+        previous = Some(frame);
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key_event) = event::read()? {
-                if key_event.kind == KeyEventKind::Press {
+            let now = Instant::now();
+            let keystrokes = session.keystrokes().len();
+            match event::read()? {
+                // Held keys repeat, as they would in any other text field.
+                Event::Key(key_event)
+                    if matches!(key_event.kind, KeyEventKind::Press | KeyEventKind::Repeat) =>
+                {
                     match key_event.code {
-                        KeyCode::Esc => break,
-                        KeyCode::Char(c) => {
-                            if start_time.is_none() {
-                                start_time = Some(Instant::now());
+                        code if code == keys.finish => {
+                            if let Some(accent) = composer.flush() {
+                                session.type_char(accent, now);
                             }
-
-                            typed.push(c);
-                            total_typed_chars += 1;
-
-                            if let Some(expected) = target_text.chars().nth(typed.len() - 1) {
-                                if c == expected {
-                                    correct_chars += 1;
-                                }
-                            }
-
-                            if typed.len() >= target_text.len() {
-                                current_line_set += 1;
-                                typed.clear();
+                            session.finish(now);
+                            break;
+                        }
+                        KeyCode::Char(c) => {
+                            let expected = session.target().get(session.caret()).cloned();
+                            for c in composer.feed(c, expected.as_deref()) {
+                                session.type_char(c, now);
                             }
                         }
-                        KeyCode::Backspace => {
-                            if let Some(removed_char) = typed.pop() {
-                                if total_typed_chars > 0 {
-                                    total_typed_chars -= 1;
-                                    if let Some(expected) = target_text.chars().nth(typed.len()) {
-                                        if removed_char == expected && correct_chars > 0 {
-                                            correct_chars = correct_chars.saturating_sub(1);
-                                        }
-                                    }
-                                }
+                        KeyCode::Enter if is_code => {
+                            if let Some(accent) = composer.flush() {
+                                session.type_char(accent, now);
                             }
+                            session.type_char('\n', now);
                         }
+                        KeyCode::Backspace if composer.pending().is_some() => composer.cancel(),
+                        KeyCode::Backspace => session.backspace(now),
                         _ => {}
                     }
                 }
+                Event::Paste(text) if settings.allow_paste => {
+                    for c in composer
+                        .flush()
+                        .into_iter()
+                        .chain(pasted_chars(&text, is_code))
+                    {
+                        session.type_char(c, now);
+                    }
+                }
+                _ => {}
+            }
+            if let (Some(clicker), Some(keystroke)) = (
+                &clicker,
+                session.keystrokes().get(keystrokes..).and_then(<[_]>::last),
+            ) {
+                if matches!(keystroke.action, KeyAction::Char { .. }) {
+                    clicker.click(keystroke.is_correct());
+                }
+            }
+        }
+    }
+
+    let mut save_error = None;
+    let mut pb_outcome = None;
+    if session.is_started() {
+        let entry = HistoryEntry::from_session(&session, lang, Instant::now());
+        if let Err(e) = history::append_entry(&entry) {
+            save_error = Some(format!("could not save history: {}", e));
+        }
+        if let Err(e) = keystats::record_session(&session, lang) {
+            save_error = Some(format!("could not save key statistics: {}", e));
+        }
+        // Drills repeat a handful of short sequences, so their speeds aren't
+        // comparable with real words, and a test ended early or failed
+        // isn't a result for its mode.
+        if !session.is_drill() && session.completed() {
+            match pb::record_result(&entry) {
+                Ok(outcome) => pb_outcome = Some(outcome),
+                Err(e) => save_error = Some(format!("could not save personal best: {}", e)),
             }
         }
     }
 
     show_results(
         &mut stdout,
-        correct_chars,
-        total_typed_chars,
-        start_time,
+        &session,
         lang,
-    )?;
+        settings,
+        pb_outcome.as_ref(),
+        save_error.as_deref(),
+    )
+}
+
+fn draw_pb_line(
+    frame: &mut Frame,
+    theme: &Theme,
+    terminal_width: u16,
+    y_pos: u16,
+    wpm: f64,
+    outcome: &PbOutcome,
+) {
+    let (text, color) = match (&outcome.previous, outcome.is_new_best) {
+        (Some(prev), true) => (
+            format!(
+                "new personal best! +{:.0} wpm (previous {:.0} wpm)",
+                wpm - prev.wpm,
+                prev.wpm
+            ),
+            theme.great,
+        ),
+        (None, true) => ("new personal best!".to_string(), theme.great),
+        (Some(prev), false) => (
+            format!("personal best: {:.0} wpm ({:.0})", prev.wpm, wpm - prev.wpm),
+            theme.subtle,
+        ),
+        (None, false) => return,
+    };
 
-    Ok(())
+    let text_x = center_text(&text, terminal_width);
+    frame.move_to(text_x, y_pos);
+    frame.set_fg(color);
+    frame.print(&text);
+    frame.reset_color();
 }
 
-fn show_results(
-    stdout: &mut std::io::Stdout,
-    correct_chars: usize,
-    total_typed_chars: usize,
-    start_time: Option<Instant>,
-    lang: &str,
-) -> std::io::Result<()> {
-    stdout.execute(terminal::Clear(ClearType::All))?;
+const GRAPH_HEIGHT: u16 = 8;
+const BAR_EIGHTHS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
-    let (terminal_width, terminal_height) = terminal::size()?;
-    let center_y = terminal_height / 2;
+struct GraphColumn {
+    wpm: f64,
+    raw_wpm: f64,
+    errors: u32,
+}
 
-    let actual_duration = start_time.map(|t| t.elapsed()).unwrap_or(Duration::ZERO);
-    let seconds_taken = actual_duration.as_secs_f64();
-    let accuracy = if total_typed_chars > 0 {
-        (correct_chars as f64 / total_typed_chars as f64) * 100.0
-    } else {
-        0.0
-    };
-    let minutes = seconds_taken / 60.0;
-    let wpm = if minutes > 0.0 {
-        correct_chars as f64 / 5.0 / minutes
+/// Fits the per-second samples to the graph width: short tests stretch each
+/// second over several columns, long tests average seconds into one column.
+fn graph_columns(samples: &[SpeedSample], max_width: usize) -> Vec<GraphColumn> {
+    let count = samples.len();
+    if count == 0 || max_width == 0 {
+        return Vec::new();
+    }
+
+    if count <= max_width {
+        let repeat = max_width / count;
+        return samples
+            .iter()
+            .flat_map(|s| {
+                (0..repeat).map(move |i| GraphColumn {
+                    wpm: s.wpm,
+                    raw_wpm: s.raw_wpm,
+                    errors: if i == 0 { s.errors } else { 0 },
+                })
+            })
+            .collect();
+    }
+
+    (0..max_width)
+        .map(|c| {
+            let bucket = &samples[c * count / max_width..(c + 1) * count / max_width];
+            let len = bucket.len().max(1) as f64;
+            GraphColumn {
+                wpm: bucket.iter().map(|s| s.wpm).sum::<f64>() / len,
+                raw_wpm: bucket.iter().map(|s| s.raw_wpm).sum::<f64>() / len,
+                errors: bucket.iter().map(|s| s.errors).sum(),
+            }
+        })
+        .collect()
+}
+
+/// Draws raw WPM as bars with the running WPM as a line of dots over them,
+/// followed by an axis, a row of error markers and a legend. Returns the
+/// number of rows used.
+fn draw_speed_graph(
+    frame: &mut Frame,
+    theme: &Theme,
+    terminal_width: u16,
+    top: u16,
+    height: u16,
+    samples: &[SpeedSample],
+) -> u16 {
+    let max_width = terminal_width.saturating_sub(16).min(100) as usize;
+    let columns = graph_columns(samples, max_width);
+    if columns.is_empty() {
+        return 0;
+    }
+
+    let peak = columns
+        .iter()
+        .map(|c| c.wpm.max(c.raw_wpm))
+        .fold(0.0, f64::max);
+    let scale = ((peak / 10.0).ceil() * 10.0).max(10.0);
+    let label = format!("{:.0}", scale);
+    let gutter = label.len() as u16 + 2;
+    let left = center_text(&" ".repeat(columns.len() + gutter as usize), terminal_width);
+    let plot_x = left + gutter;
+
+    for row in 0..height {
+        let level = (height - 1 - row) as f64;
+        frame.move_to(left, top + row);
+        frame.set_fg(theme.subtle);
+        if row == 0 {
+            frame.print(format!("{} ┤", label));
+        } else {
+            frame.print(format!("{:>width$}", "│", width = gutter as usize));
+        }
+
+        frame.move_to(plot_x, top + row);
+        for column in &columns {
+            let eighths = (column.raw_wpm / scale * height as f64 * 8.0).round() as i64;
+            let filled = eighths - level as i64 * 8;
+            let bar = match filled {
+                8.. => Some('█'),
+                1..=7 => Some(BAR_EIGHTHS[filled as usize - 1]),
+                _ => None,
+            };
+            let on_line = (column.wpm / scale * (height - 1) as f64).round() == level;
+
+            if on_line {
+                if bar == Some('█') {
+                    frame.set_bg(theme.subtle);
+                }
+                frame.set_fg(theme.accent);
+                frame.print('•');
+                frame.reset_color();
+            } else {
+                frame.set_fg(theme.subtle);
+                frame.print(bar.unwrap_or(' '));
+            }
+        }
+    }
+
+    let axis_y = top + height;
+    frame.move_to(left, axis_y);
+    frame.set_fg(theme.subtle);
+    frame.print(format!(
+        "{:>width$}{}",
+        "0 └",
+        "─".repeat(columns.len()),
+        width = gutter as usize
+    ));
+
+    frame.move_to(plot_x, axis_y + 1);
+    frame.set_fg(theme.incorrect);
+    for column in &columns {
+        frame.print(if column.errors > 0 { 'x' } else { ' ' });
+    }
+
+    let legend = format!("• wpm  █ raw  x errors  ({}s)", samples.len());
+    let legend_x = center_text(&legend, terminal_width);
+    frame.move_to(legend_x, axis_y + 2);
+    frame.set_fg(theme.subtle);
+    frame.print(&legend);
+    frame.reset_color();
+
+    height + 3
+}
+
+/// Lists the missed words on one line, cutting the list short with a count
+/// of the rest if it doesn't fit.
+fn draw_missed_line(
+    frame: &mut Frame,
+    theme: &Theme,
+    terminal_width: u16,
+    y_pos: u16,
+    missed: &[String],
+) {
+    let label = "missed: ";
+    let max_width = terminal_width.saturating_sub(4) as usize;
+    // Leave room for the "+N more" tail unless this is the last word.
+    let reserve = " +999 more".len();
+    let mut shown = String::new();
+    let mut count = 0;
+    for word in missed {
+        let tail = if count + 1 < missed.len() { reserve } else { 0 };
+        let width = label.len() + shown.width() + 1 + word.width() + tail;
+        if count > 0 && width > max_width {
+            break;
+        }
+        if count > 0 {
+            shown.push(' ');
+        }
+        shown.push_str(word);
+        count += 1;
+    }
+    let more = if count < missed.len() {
+        format!(" +{} more", missed.len() - count)
     } else {
-        0.0
+        String::new()
     };
 
+    let line_x = center_text(&format!("{}{}{}", label, shown, more), terminal_width);
+    frame.move_to(line_x, y_pos);
+    frame.set_fg(theme.subtle);
+    frame.print(label);
+    frame.set_fg(theme.incorrect);
+    frame.print(&shown);
+    frame.set_fg(theme.subtle);
+    frame.print(&more);
+    frame.reset_color();
+}
+
+/// Shows the results until the user picks what to do next, redrawing
+/// whenever the terminal is resized.
+fn show_results(
+    stdout: &mut Stdout,
+    session: &TypingSession,
+    lang: &str,
+    settings: &TuiSettings,
+    pb_outcome: Option<&PbOutcome>,
+    save_error: Option<&str>,
+) -> io::Result<NextTest> {
+    let keys = &settings.keys;
+    let missed = session.missed_words();
+    let mut previous: Option<Frame> = None;
+
+    loop {
+        let (terminal_width, terminal_height) = terminal::size()?;
+        let mut frame = Frame::new(terminal_width, terminal_height);
+        let (min_width, min_height) =
+            draw_results(&mut frame, session, lang, settings, pb_outcome, save_error);
+        if terminal_width < min_width || terminal_height < min_height {
+            frame = Frame::new(terminal_width, terminal_height);
+            draw_too_small(&mut frame, &settings.theme, min_width, min_height);
+        }
+        frame.render(stdout, previous.as_ref())?;
+        previous = Some(frame);
+
+        // Anything other than a bound key, such as a resize, just redraws.
+        if let Event::Key(key_event) = event::read()? {
+            if key_event.kind == KeyEventKind::Press {
+                match key_event.code {
+                    code if code == keys.restart => return Ok(NextTest::Restart),
+                    code if code == keys.retry && !missed.is_empty() => {
+                        return Ok(NextTest::Retry(missed));
+                    }
+                    code if code == keys.quit => return Ok(NextTest::Quit),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Draws the results screen and returns the smallest terminal size it fits
+/// in, without the speed graph, which is left out when there isn't room.
+fn draw_results(
+    frame: &mut Frame,
+    session: &TypingSession,
+    lang: &str,
+    settings: &TuiSettings,
+    pb_outcome: Option<&PbOutcome>,
+    save_error: Option<&str>,
+) -> (u16, u16) {
+    let keys = &settings.keys;
+    let theme = &settings.theme;
+
+    let (terminal_width, terminal_height) = frame.size();
+    let now = Instant::now();
+
+    // The graph needs its own rows below the summary, so shift everything up
+    // to keep the whole screen centred. Small terminals just skip the graph.
+    let samples = session.speed_samples(now);
+    let graph_height = GRAPH_HEIGHT.min(terminal_height.saturating_sub(16));
+    let show_graph = samples.len() >= 2 && graph_height >= 3 && terminal_width >= 40;
+    let graph_rows = if show_graph { graph_height + 4 } else { 0 };
+    let center_y = terminal_height.saturating_sub(graph_rows) / 2;
+
+    let seconds_taken = session.elapsed(now).as_secs_f64();
+    let accuracy = session.accuracy().unwrap_or(0.0);
+    let wpm = session.wpm(now);
+
     let title = "── results ──";
     let title_x = center_text(title, terminal_width);
-    stdout.execute(cursor::MoveTo(title_x, center_y - 3))?;
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(title))?;
-    stdout.execute(ResetColor)?;
-
-    let results_x = center_text(
-        &format!(
-            "{:.0} wpm │ {:.0}% acc │ {:.0}s │ {}",
-            wpm, accuracy, seconds_taken, lang
-        ),
-        terminal_width,
+    frame.move_to(title_x, center_y.saturating_sub(3));
+    frame.set_fg(theme.subtle);
+    frame.print(title);
+    frame.reset_color();
+
+    let results_line = format!(
+        "{:.0} wpm │ {:.0}% acc │ {:.0}s │ {}",
+        wpm, accuracy, seconds_taken, lang
     );
-    stdout.execute(cursor::MoveTo(results_x, center_y))?;
+    let results_x = center_text(&results_line, terminal_width);
+    frame.move_to(results_x, center_y);
 
     let wpm_color = match wpm as u32 {
-        60.. => Color::Green,
-        40..=59 => Color::Cyan,
-        20..=39 => Color::Yellow,
-        _ => Color::Red,
+        60.. => theme.great,
+        40..=59 => theme.good,
+        20..=39 => theme.fair,
+        _ => theme.poor,
     };
-    stdout.execute(SetForegroundColor(wpm_color))?;
-    stdout.execute(Print(&format!("{:.0}", wpm)))?;
+    frame.set_fg(wpm_color);
+    frame.print(format!("{:.0}", wpm));
 
-    stdout.execute(SetForegroundColor(Color::Grey))?;
-    stdout.execute(Print(" wpm"))?;
+    frame.set_fg(theme.text);
+    frame.print(" wpm");
 
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(" │ "))?;
+    frame.set_fg(theme.subtle);
+    frame.print(" │ ");
 
     let acc_color = match accuracy as u32 {
-        95..=100 => Color::Green,
-        80..=94 => Color::Yellow,
-        _ => Color::Red,
+        95..=100 => theme.great,
+        80..=94 => theme.fair,
+        _ => theme.poor,
     };
-    stdout.execute(SetForegroundColor(acc_color))?;
-    stdout.execute(Print(&format!("{:.0}", accuracy)))?;
+    frame.set_fg(acc_color);
+    frame.print(format!("{:.0}", accuracy));
 
-    stdout.execute(SetForegroundColor(Color::Grey))?;
-    stdout.execute(Print("% acc"))?;
+    frame.set_fg(theme.text);
+    frame.print("% acc");
 
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(" │ "))?;
+    frame.set_fg(theme.subtle);
+    frame.print(" │ ");
 
-    stdout.execute(SetForegroundColor(Color::Grey))?;
-    stdout.execute(Print(&format!("{:.0}s", seconds_taken)))?;
+    frame.set_fg(theme.text);
+    frame.print(format!("{:.0}s", seconds_taken));
 
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(" │ "))?;
+    frame.set_fg(theme.subtle);
+    frame.print(" │ ");
 
-    stdout.execute(SetForegroundColor(Color::Grey))?;
-    stdout.execute(Print(lang))?;
-    stdout.execute(ResetColor)?;
+    frame.set_fg(theme.text);
+    frame.print(lang);
+    frame.reset_color();
 
-    let instructions = "tab: restart • esc: quit";
-    let inst_x = center_text(instructions, terminal_width);
-    stdout.execute(cursor::MoveTo(inst_x, center_y + 3))?;
-    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
-    stdout.execute(Print(instructions))?;
-    stdout.execute(ResetColor)?;
+    let mut next_y = center_y + 1;
 
-    loop {
-        if let Event::Key(key_event) = event::read()? {
-            if key_event.kind == KeyEventKind::Press {
-                match key_event.code {
-                    KeyCode::Tab => {
-                        break;
-                    }
-                    KeyCode::Esc => {
-                        stdout.execute(cursor::Show)?;
-                        terminal::disable_raw_mode()?;
-                        std::process::exit(0);
-                    }
-                    _ => {}
-                }
+    let consistency = session
+        .consistency(now)
+        .map_or("-".to_string(), |c| format!("{:.0}%", c));
+    let mut detail = format!(
+        "raw {:.0} │ net {:.0} │ consistency {}",
+        session.raw_wpm(now),
+        session.net_wpm(now),
+        consistency
+    );
+    if session.rules().strict.is_some() {
+        detail.push_str(&format!(" │ corrections {}", session.corrections()));
+    }
+    let detail_x = center_text(&detail, terminal_width);
+    frame.move_to(detail_x, next_y);
+    frame.set_fg(theme.subtle);
+    frame.print(&detail);
+    frame.reset_color();
+    next_y += 1;
+
+    if let Some(failure) = session.failure() {
+        let failed = format!("failed: {}", failure.reason());
+        frame.move_to(center_text(&failed, terminal_width), next_y);
+        frame.set_fg(theme.incorrect);
+        frame.print(&failed);
+        frame.reset_color();
+        next_y += 1;
+    }
+
+    if session.is_drill() {
+        let mut word_stats = session.word_stats();
+        word_stats.sort_by(|a, b| a.wpm().total_cmp(&b.wpm()));
+        let max_width = terminal_width.saturating_sub(4) as usize;
+        let mut breakdown = String::from("slowest first:");
+        for stat in &word_stats {
+            let item = format!(" {} {:.0}", stat.word, stat.wpm());
+            if breakdown.width() + item.width() + 2 > max_width {
+                break;
             }
+            if !breakdown.ends_with(':') {
+                breakdown.push_str(" │");
+            }
+            breakdown.push_str(&item);
+        }
+        if !word_stats.is_empty() {
+            let breakdown_x = center_text(&breakdown, terminal_width);
+            frame.move_to(breakdown_x, next_y);
+            frame.set_fg(theme.text);
+            frame.print(&breakdown);
+            frame.reset_color();
+            next_y += 1;
         }
     }
 
-    stdout.execute(cursor::Show)?;
-    terminal::disable_raw_mode()?;
-    Ok(())
+    if let Some(source) = session.source() {
+        let attribution = format!("— {}", source);
+        let attribution_x = center_text(&attribution, terminal_width);
+        frame.move_to(attribution_x, next_y);
+        frame.set_fg(theme.subtle);
+        frame.print(&attribution);
+        frame.reset_color();
+        next_y += 1;
+    }
+
+    if let Some(outcome) = pb_outcome {
+        draw_pb_line(frame, theme, terminal_width, next_y, wpm, outcome);
+        next_y += 1;
+    }
+
+    let missed = session.missed_words();
+    if !missed.is_empty() {
+        draw_missed_line(frame, theme, terminal_width, next_y, &missed);
+        next_y += 1;
+    }
+
+    if show_graph {
+        next_y += 1 + draw_speed_graph(
+            frame,
+            theme,
+            terminal_width,
+            next_y + 1,
+            graph_height,
+            &samples,
+        );
+    }
+
+    let instructions = if missed.is_empty() {
+        format!(
+            "{}: restart • {}: quit",
+            key_name(keys.restart),
+            key_name(keys.quit)
+        )
+    } else {
+        format!(
+            "{}: restart • {}: retry missed words • {}: quit",
+            key_name(keys.restart),
+            key_name(keys.retry),
+            key_name(keys.quit)
+        )
+    };
+    let inst_x = center_text(&instructions, terminal_width);
+    let inst_y = next_y.max(center_y + 2) + 1;
+    frame.move_to(inst_x, inst_y);
+    frame.set_fg(theme.subtle);
+    frame.print(&instructions);
+    frame.reset_color();
+
+    if let Some(message) = save_error {
+        let msg_x = center_text(message, terminal_width);
+        frame.move_to(msg_x, inst_y + 2);
+        frame.set_fg(theme.incorrect);
+        frame.print(message);
+        frame.reset_color();
+    }
+
+    // The title sits three rows above the centre line and everything else
+    // below it, so the screen fits once there is room for the lower part
+    // on both sides of the centre. The graph is left out of the count.
+    let bottom = if save_error.is_some() {
+        inst_y + 2
+    } else {
+        inst_y
+    };
+    let below = bottom - center_y - graph_rows;
+    let widest = [results_line.width(), instructions.width()]
+        .into_iter()
+        .max()
+        .unwrap_or(0) as u16;
+    ((widest + 2).max(MIN_WIDTH), below.max(3) * 2 + 1)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(text: &str, max_width: usize, max_words: usize) -> Vec<(usize, usize, usize)> {
+        let words: Vec<String> = text.split(' ').map(str::to_string).collect();
+        wrap_text_lines(&words, max_width, max_words)
+            .iter()
+            .map(|line| (line.start, line.len, line.width))
+            .collect()
+    }
+
+    #[test]
+    fn wrap_breaks_between_words_at_the_width_limit() {
+        assert_eq!(wrap("aaa bbb ccc", 7, usize::MAX), [(0, 7, 7), (8, 3, 3)]);
+        assert_eq!(
+            wrap("aaa bbb ccc", 6, usize::MAX),
+            [(0, 3, 3), (4, 3, 3), (8, 3, 3)]
+        );
+    }
+
+    #[test]
+    fn wrap_stops_at_max_words() {
+        assert_eq!(wrap("a b c d e", 80, 2), [(0, 3, 3), (4, 3, 3), (8, 1, 1)]);
+    }
+
+    #[test]
+    fn wrap_counts_wide_graphemes_by_columns() {
+        // Each of these takes two columns but is one grapheme.
+        assert_eq!(wrap("日本 語", 5, usize::MAX), [(0, 2, 4), (3, 1, 2)]);
+        assert_eq!(wrap("日本 語", 7, usize::MAX), [(0, 4, 7)]);
+    }
+
+    #[test]
+    fn wrap_splits_a_word_wider_than_a_line() {
+        assert_eq!(
+            wrap("ab cdefghij k", 4, usize::MAX),
+            [(0, 2, 2), (3, 4, 4), (7, 4, 4), (12, 1, 1)]
+        );
+        assert_eq!(wrap("日本語", 5, usize::MAX), [(0, 2, 4), (2, 1, 2)]);
+    }
+}